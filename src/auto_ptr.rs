@@ -81,11 +81,19 @@ impl<T> Drop for HeapPointer<T> {
 }
 
 pub(crate) trait ArrayExt<T> {
+    /// Leaks the vector as `(ptr, size, capacity)` for a C struct.
+    ///
+    /// Whoever stores these must keep all three unchanged and give them back
+    /// to `Vec::from_raw_parts` to free the array. The capacity is the true
+    /// size of the allocation, freeing with any other value corrupts the heap.
     fn into_c_layout(self) -> (*mut T, usize, usize);
 }
 
 impl<T> ArrayExt<T> for Vec<T> {
-    fn into_c_layout(self) -> (*mut T, usize, usize) {
+    fn into_c_layout(mut self) -> (*mut T, usize, usize) {
+        // Trim spare capacity so the native side, which only reads the size,
+        // sees an array that fills its whole allocation.
+        self.shrink_to_fit();
         let mut me = ManuallyDrop::new(self);
         (me.as_mut_ptr(), me.len(), me.capacity())
     }
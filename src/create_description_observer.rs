@@ -2,17 +2,15 @@ use std::{
     error::Error,
     ffi::{c_char, c_void},
     fmt,
-    sync::{
-        atomic::{AtomicPtr, Ordering},
-        Arc,
-    },
+    sync::Arc,
 };
 
 use futures::task::AtomicWaker;
 
 use crate::{
     cstr::{from_c_str, StringError},
-    rtc_peerconnection::RawRTCPeerConnection,
+    promisify::CallbackSlot,
+    rtc_peerconnection::{hold_open, Closed, RawRTCPeerConnection},
     rtc_session_description::RawRTCSessionDescription,
     Promisify, PromisifyExt, RTCSessionDescription,
 };
@@ -43,13 +41,21 @@ extern "C" {
 pub enum CreateDescriptionError {
     StringError(StringError),
     CreateFailed(String),
+    /// The peer connection has been closed.
+    Closed,
 }
 
 impl Error for CreateDescriptionError {}
 
 impl fmt::Display for CreateDescriptionError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self)
+        match self {
+            Self::StringError(e) => write!(f, "invalid string: {}", e),
+            Self::CreateFailed(message) => {
+                write!(f, "failed to create the description: {}", message)
+            }
+            Self::Closed => write!(f, "the peer connection is closed"),
+        }
     }
 }
 
@@ -87,7 +93,8 @@ extern "C" fn create_description_callback(
 pub struct CreateDescriptionObserver {
     kind: CreateDescriptionKind,
     pc: *const RawRTCPeerConnection,
-    ret: Arc<AtomicPtr<Result<RTCSessionDescription, CreateDescriptionError>>>,
+    closed: Closed,
+    ret: Arc<CallbackSlot<Result<RTCSessionDescription, CreateDescriptionError>>>,
 }
 
 unsafe impl Send for CreateDescriptionObserver {}
@@ -98,10 +105,14 @@ impl PromisifyExt for CreateDescriptionObserver {
     type Err = CreateDescriptionError;
 
     fn handle(&self, waker: Arc<AtomicWaker>) -> Result<(), Self::Err> {
+        // Checked on the first poll, which is when the native call is made,
+        // rather than when the future is created.
+        let _open = hold_open(&self.closed).ok_or(CreateDescriptionError::Closed)?;
+
         let ret = self.ret.clone();
         let ctx = Box::into_raw(Box::new(CreateDescriptionContext {
             callback: Box::new(move |res| {
-                ret.store(Box::into_raw(Box::new(res)), Ordering::Relaxed);
+                ret.put(res);
                 waker.wake();
             }),
         })) as *mut c_void;
@@ -116,20 +127,20 @@ impl PromisifyExt for CreateDescriptionObserver {
     }
 
     fn wake(&self) -> Option<Result<Self::Output, Self::Err>> {
-        unsafe {
-            self.ret
-                .swap(std::ptr::null_mut(), Ordering::Relaxed)
-                .as_mut()
-        }
-        .map(|ptr| unsafe { *Box::from_raw(ptr) })
+        self.ret.take()
     }
 }
 
 pub type CreateDescriptionFuture = Promisify<CreateDescriptionObserver>;
 impl CreateDescriptionFuture {
-    pub(crate) fn create(pc: *const RawRTCPeerConnection, kind: CreateDescriptionKind) -> Self {
+    pub(crate) fn create(
+        pc: *const RawRTCPeerConnection,
+        kind: CreateDescriptionKind,
+        closed: Closed,
+    ) -> Self {
         Promisify::new(CreateDescriptionObserver {
-            ret: Arc::new(AtomicPtr::new(std::ptr::null_mut())),
+            ret: Arc::new(CallbackSlot::new()),
+            closed,
             kind,
             pc,
         })
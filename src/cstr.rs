@@ -1,4 +1,8 @@
-use std::ffi::{c_char, CStr, CString};
+use std::{
+    error::Error,
+    ffi::{c_char, CStr, CString},
+    fmt,
+};
 
 #[derive(Debug)]
 pub enum StringError {
@@ -6,6 +10,17 @@ pub enum StringError {
     Utf8Error,
 }
 
+impl Error for StringError {}
+
+impl fmt::Display for StringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NulError => write!(f, "the string contains a NUL byte"),
+            Self::Utf8Error => write!(f, "the string is not valid UTF-8"),
+        }
+    }
+}
+
 pub(crate) fn to_c_str(str: &str) -> Result<*const c_char, StringError> {
     Ok(CString::new(str)
         .map_err(|_| StringError::NulError)?
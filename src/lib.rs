@@ -22,8 +22,13 @@ mod rtc_icecandidate;
 mod rtc_peerconnection;
 mod rtc_peerconnection_configure;
 mod rtc_session_description;
+mod sdp;
 mod set_description_observer;
 mod sink;
+#[cfg(test)]
+mod test_alloc;
+#[cfg(feature = "testing")]
+pub mod test_util;
 mod video_frame;
 mod video_track;
 
@@ -43,10 +48,12 @@ pub use rtc_datachannel::{
 pub use rtc_icecandidate::RTCIceCandidate;
 pub use rtc_peerconnection::{RTCError, RTCPeerConnection};
 pub use rtc_peerconnection_configure::{
-    BundlePolicy, IceTransportPolicy, RTCConfiguration, RTCIceServer, RtcpMuxPolicy,
+    BundlePolicy, ConfigError, IceServerError, IceTransport, IceTransportPolicy, IceUrls,
+    ParsePolicyError, RTCConfiguration, RTCConfigurationBuilder, RTCIceServer, RtcpMuxPolicy,
 };
 pub use rtc_session_description::{RTCSessionDescription, RTCSessionDescriptionType};
+pub use sdp::{MediaSection, Sdp, SdpError};
 pub use set_description_observer::{SetDescriptionError, SetDescriptionObserver};
 pub use sink::{SinkExt, Sinker};
-pub use video_frame::VideoFrame;
+pub use video_frame::{FrameError, VideoFrame, VideoFrameRef};
 pub use video_track::VideoTrack;
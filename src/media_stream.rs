@@ -15,7 +15,10 @@ impl Error for MediaStreamError {}
 
 impl fmt::Display for MediaStreamError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self)
+        match self {
+            Self::CreateTrackFailed => write!(f, "failed to create the track"),
+            Self::StringError(e) => write!(f, "invalid string: {}", e),
+        }
     }
 }
 
@@ -65,6 +65,15 @@ impl MediaStreamTrack {
         Ok(Self::Audio(AudioTrack::new(label)?))
     }
 
+    /// Returns [`MediaStreamTrackKind::Audio`] for an audio track and
+    /// [`MediaStreamTrackKind::Video`] for a video track.
+    pub fn kind(&self) -> MediaStreamTrackKind {
+        match self {
+            Self::Audio(_) => MediaStreamTrackKind::Audio,
+            Self::Video(_) => MediaStreamTrackKind::Video,
+        }
+    }
+
     /// Created through the original media stream track, video and audio
     /// are processed separately.
     pub(crate) fn from_raw(raw: *const RawMediaStreamTrack) -> Self {
@@ -1,4 +1,10 @@
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
 
 use crate::{
     media_stream_track::RawMediaStreamTrack, rtc_datachannel::RawRTCDataChannel,
@@ -41,7 +47,7 @@ pub enum PeerConnectionState {
 /// Describes the state of the signaling process at the local end
 /// of the connection when connecting or reconnecting to another peer.
 #[repr(i32)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SignalingState {
     /// There is no ongoing exchange of offer and answer underway.
     Stable,
@@ -69,6 +75,19 @@ pub enum SignalingState {
     Closed,
 }
 
+impl From<i32> for SignalingState {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => Self::Stable,
+            1 => Self::HaveLocalOffer,
+            2 => Self::HaveLocalPrAnswer,
+            3 => Self::HaveRemoteOffer,
+            4 => Self::HaveRemotePrAnswer,
+            _ => Self::Closed,
+        }
+    }
+}
+
 /// Describes the ICE collection status of the connection.
 #[repr(i32)]
 #[derive(Clone, Copy, Debug)]
@@ -131,6 +150,12 @@ pub enum IceConnectionState {
 
 /// PeerConnection callback interface, used for RTCPeerConnection events.
 /// Application should implement these methods.
+///
+/// All methods are called on the native signaling thread, one at a time and
+/// in the order the events occur. They should return quickly, a blocked
+/// callback holds up every other event of the connection. Do not wait on
+/// an RTCPeerConnection future from inside a callback, its result is
+/// delivered on the same thread.
 #[allow(unused)]
 pub trait Observer {
     /// A signalingstatechange event is sent to an RTCPeerConnection to notify
@@ -180,17 +205,38 @@ pub trait Observer {
     fn on_data_channel(&self, channel: RTCDataChannel) {}
 }
 
+/// Decides whether a locally gathered candidate is passed to
+/// [`Observer::on_ice_candidate`].
+pub(crate) type CandidateFilter =
+    Arc<Mutex<Option<Box<dyn FnMut(&RTCIceCandidate) -> bool + Send>>>>;
+
 /// wrapper observer trait impl.
 pub struct ObserverRef {
     data: Box<dyn Observer>,
+    signaling_state: Arc<AtomicI32>,
+    candidate_filter: CandidateFilter,
 }
 
 impl ObserverRef {
     pub fn new<T: Observer + 'static>(data: T) -> Self {
         Self {
             data: Box::new(data),
+            signaling_state: Arc::new(AtomicI32::new(SignalingState::Stable as i32)),
+            candidate_filter: Arc::new(Mutex::new(None)),
         }
     }
+
+    /// The last signaling state reported by the native connection, shared
+    /// with the peer connection so it can validate descriptions.
+    pub(crate) fn signaling_state(&self) -> Arc<AtomicI32> {
+        self.signaling_state.clone()
+    }
+
+    /// The filter applied to gathered candidates, shared with the peer
+    /// connection so it can be replaced at any time.
+    pub(crate) fn candidate_filter(&self) -> CandidateFilter {
+        self.candidate_filter.clone()
+    }
 }
 
 /// rtc peer connection observer events callback ref.
@@ -221,7 +267,9 @@ pub(crate) const EVENTS: TEvents = TEvents {
 
 extern "C" fn on_signaling_change(ctx: *mut ObserverRef, state: SignalingState) {
     assert!(!ctx.is_null());
-    (unsafe { &mut *ctx }).data.on_signaling_change(state);
+    let ctx = unsafe { &mut *ctx };
+    ctx.signaling_state.store(state as i32, Ordering::Release);
+    ctx.data.on_signaling_change(state);
 }
 
 extern "C" fn on_connection_change(ctx: *mut ObserverRef, state: PeerConnectionState) {
@@ -237,8 +285,37 @@ extern "C" fn on_ice_gathering_change(ctx: *mut ObserverRef, state: IceGathering
 extern "C" fn on_ice_candidate(ctx: *mut ObserverRef, candidate: *const RawRTCIceCandidate) {
     assert!(!ctx.is_null());
     assert!(!candidate.is_null());
-    let candidate = RTCIceCandidate::try_from(unsafe { &*candidate }).unwrap();
-    (unsafe { &mut *ctx }).data.on_ice_candidate(candidate);
+    let Ok(candidate) = RTCIceCandidate::try_from(unsafe { &*candidate }) else {
+        return;
+    };
+
+    // The filter is taken out while it runs, so user code never runs with
+    // the lock held. This callback only runs on the signaling thread, so no
+    // other candidate can miss the filter meanwhile.
+    let ctx = unsafe { &mut *ctx };
+    let filter = ctx
+        .candidate_filter
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    if let Some(mut filter) = filter {
+        let keep = filter(&candidate);
+
+        // Put it back, unless set_candidate_filter replaced it meanwhile.
+        let mut slot = ctx
+            .candidate_filter
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if slot.is_none() {
+            *slot = Some(filter);
+        }
+
+        if !keep {
+            return;
+        }
+    }
+
+    ctx.data.on_ice_candidate(candidate);
 }
 
 extern "C" fn on_renegotiation_needed(ctx: *mut ObserverRef) {
@@ -12,6 +12,43 @@ use std::{
 
 use futures::task::AtomicWaker;
 
+/// The result slot shared between a callback future and its native callback.
+///
+/// Both sides hold it through an `Arc`, so dropping the future before the
+/// callback fires is safe: the late callback stores into a slot that is still
+/// alive, and the result is freed with the slot when the last side lets go.
+pub(crate) struct CallbackSlot<T>(AtomicPtr<T>);
+
+impl<T> CallbackSlot<T> {
+    pub fn new() -> Self {
+        Self(AtomicPtr::new(std::ptr::null_mut()))
+    }
+
+    pub fn put(&self, value: T) {
+        let prev = self
+            .0
+            .swap(Box::into_raw(Box::new(value)), Ordering::AcqRel);
+        if !prev.is_null() {
+            drop(unsafe { Box::from_raw(prev) });
+        }
+    }
+
+    pub fn take(&self) -> Option<T> {
+        let ptr = self.0.swap(std::ptr::null_mut(), Ordering::AcqRel);
+        if ptr.is_null() {
+            None
+        } else {
+            Some(unsafe { *Box::from_raw(ptr) })
+        }
+    }
+}
+
+impl<T> Drop for CallbackSlot<T> {
+    fn drop(&mut self) {
+        let _ = self.take();
+    }
+}
+
 pub trait PromisifyExt {
     type Err;
     type Output;
@@ -20,6 +57,13 @@ pub trait PromisifyExt {
     fn wake(&self) -> Option<Result<Self::Output, Self::Err>>;
 }
 
+/// A plain [`Future`] over a native callback, it is not tied to any async
+/// runtime and spawns nothing.
+///
+/// The native call is started on the first poll. The callback usually fires
+/// on a native thread, it stores the result in a shared slot and wakes the
+/// task through an [`AtomicWaker`], which is safe to do from any thread and
+/// even before the first poll has returned.
 pub struct Promisify<T>
 where
     T: PromisifyExt,
@@ -167,3 +211,38 @@ where
         Poll::Pending
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_alloc::outstanding;
+
+    #[test]
+    fn late_result_is_freed_with_the_slot() {
+        let leaked = outstanding(|| {
+            let slot = Arc::new(CallbackSlot::new());
+            let callback = slot.clone();
+            drop(slot);
+            callback.put("late".to_string());
+        });
+        assert_eq!(leaked, (0, 0));
+    }
+
+    #[test]
+    fn second_put_frees_the_first() {
+        let slot = CallbackSlot::new();
+        slot.put("first!".to_string());
+        let leaked = outstanding(|| slot.put("second".to_string()));
+        assert_eq!(leaked, (0, 0));
+        assert_eq!(slot.take().as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn take_empties_the_slot() {
+        let slot = CallbackSlot::new();
+        assert_eq!(slot.take(), None::<String>);
+        slot.put("value".to_string());
+        assert_eq!(slot.take().as_deref(), Some("value"));
+        assert_eq!(slot.take(), None);
+    }
+}
@@ -6,7 +6,7 @@ use std::{
 };
 
 use crate::{
-    cstr::{free_cstring, to_c_str},
+    cstr::{free_cstring, to_c_str, StringError},
     Sinker,
 };
 
@@ -130,18 +130,28 @@ impl Default for DataChannelOptions {
     }
 }
 
-impl Into<RawDataChannelOptions> for &DataChannelOptions {
-    fn into(self) -> RawDataChannelOptions {
-        RawDataChannelOptions {
+impl DataChannelOptions {
+    /// `max_retransmit_time` and `max_retransmits` both limit retransmission,
+    /// the spec allows at most one of them to be set.
+    pub(crate) fn is_valid(&self) -> bool {
+        !(self.max_retransmit_time.is_some() && self.max_retransmits.is_some())
+    }
+}
+
+impl TryInto<RawDataChannelOptions> for &DataChannelOptions {
+    type Error = StringError;
+
+    fn try_into(self) -> Result<RawDataChannelOptions, Self::Error> {
+        Ok(RawDataChannelOptions {
             id: self.id as c_int,
             reliable: self.reliable,
             ordered: self.ordered,
             negotiated: self.negotiated,
-            protocol: to_c_str(&self.protocol).unwrap(),
+            protocol: to_c_str(&self.protocol)?,
             max_retransmits: self.max_retransmits.unwrap_or(0),
             max_retransmit_time: self.max_retransmit_time.unwrap_or(0),
             priority: self.priority.as_ref().map(|x| *x as c_int).unwrap_or(0),
-        }
+        })
     }
 }
 
@@ -232,3 +242,33 @@ extern "C" fn on_channal_data(ctx: &DataChannel, buf: *const u8, size: u64) {
     let array = unsafe { from_raw_parts(buf, size as usize) };
     DataChannel::on_data(ctx, array.to_vec());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn at_most_one_retransmission_limit() {
+        let options = |max_retransmit_time, max_retransmits| DataChannelOptions {
+            max_retransmit_time,
+            max_retransmits,
+            ..Default::default()
+        };
+
+        assert!(options(None, None).is_valid());
+        assert!(options(Some(500), None).is_valid());
+        assert!(options(None, Some(3)).is_valid());
+        assert!(!options(Some(500), Some(3)).is_valid());
+    }
+
+    #[test]
+    fn nul_in_protocol_is_an_error() {
+        let options = DataChannelOptions {
+            protocol: "chat\0".to_string(),
+            ..Default::default()
+        };
+
+        let raw: Result<RawDataChannelOptions, _> = (&options).try_into();
+        assert!(matches!(raw, Err(StringError::NulError)));
+    }
+}
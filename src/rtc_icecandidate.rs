@@ -1,8 +1,15 @@
-use std::ffi::{c_char, c_int};
+use std::{
+    ffi::{c_char, c_int},
+    sync::Mutex,
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::cstr::{free_cstring, from_c_str, to_c_str, StringError};
+use crate::{
+    cstr::{free_cstring, from_c_str, to_c_str, StringError},
+    rtc_peerconnection::{rtc_add_ice_candidate, RawRTCPeerConnection},
+    RTCError,
+};
 
 #[repr(C)]
 pub(crate) struct RawRTCIceCandidate {
@@ -18,6 +25,8 @@ impl Drop for RawRTCIceCandidate {
     }
 }
 
+unsafe impl Send for RawRTCIceCandidate {}
+
 /// Indicates a candidate Interactive Connection Establishment
 /// (ICE) configuration.
 ///
@@ -34,7 +43,7 @@ impl Drop for RawRTCIceCandidate {
 ///
 /// For details on how the ICE process works, see Lifetime of a WebRTC session.
 /// The article WebRTC connectivity provides additional useful details.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RTCIceCandidate {
     /// A string describing the properties of the candidate, taken directly
     /// from the SDP attribute "candidate". The candidate string specifies
@@ -46,12 +55,28 @@ pub struct RTCIceCandidate {
     /// A string containing the identification tag of the media stream with
     /// which the candidate is associated, or null if there is no
     /// associated media stream. The default is null.
-    pub sdp_mid: String,
+    pub sdp_mid: Option<String>,
     /// TA number property containing the zero-based index of the m-line with
     /// which Tthe candidate is associated, within the SDP of the media
     /// description, or Tnull if no such associated exists. The default is
     /// null.
-    pub sdp_mline_index: u8,
+    pub sdp_mline_index: Option<u16>,
+}
+
+impl RTCIceCandidate {
+    /// Creates a candidate from the values relayed over signaling, the
+    /// inverse of reading its public fields.
+    pub fn from_parts(
+        candidate: impl Into<String>,
+        sdp_mid: Option<String>,
+        sdp_mline_index: Option<u16>,
+    ) -> Self {
+        Self {
+            candidate: candidate.into(),
+            sdp_mline_index,
+            sdp_mid,
+        }
+    }
 }
 
 impl TryInto<RawRTCIceCandidate> for &RTCIceCandidate {
@@ -59,8 +84,8 @@ impl TryInto<RawRTCIceCandidate> for &RTCIceCandidate {
 
     fn try_into(self) -> Result<RawRTCIceCandidate, Self::Error> {
         Ok(RawRTCIceCandidate {
-            sdp_mline_index: self.sdp_mline_index as c_int,
-            sdp_mid: to_c_str(&self.sdp_mid)?,
+            sdp_mline_index: self.sdp_mline_index.map(c_int::from).unwrap_or(-1),
+            sdp_mid: to_c_str(self.sdp_mid.as_deref().unwrap_or(""))?,
             candidate: to_c_str(&self.candidate)?,
         })
     }
@@ -70,10 +95,129 @@ impl TryFrom<&RawRTCIceCandidate> for RTCIceCandidate {
     type Error = StringError;
 
     fn try_from(value: &RawRTCIceCandidate) -> Result<Self, Self::Error> {
+        let sdp_mid = if value.sdp_mid.is_null() {
+            None
+        } else {
+            Some(from_c_str(value.sdp_mid)?).filter(|mid| !mid.is_empty())
+        };
+
         Ok(RTCIceCandidate {
-            sdp_mline_index: value.sdp_mline_index as u8,
-            sdp_mid: from_c_str(value.sdp_mid)?,
+            sdp_mline_index: u16::try_from(value.sdp_mline_index).ok(),
             candidate: from_c_str(value.candidate)?,
+            sdp_mid,
         })
     }
 }
+
+/// Remote candidates received before the remote description.
+///
+/// Like a browser, the peer connection holds on to these until a remote
+/// description is applied, since the native ICE agent has nothing to attach
+/// them to before that.
+pub(crate) struct PendingCandidates {
+    queue: Mutex<Option<Vec<RawRTCIceCandidate>>>,
+}
+
+impl PendingCandidates {
+    pub fn new() -> Self {
+        Self {
+            queue: Mutex::new(Some(Vec::new())),
+        }
+    }
+
+    /// Adds the candidate to the connection, or queues it if there is no
+    /// remote description yet.
+    ///
+    /// The lock is never held across the native call, which may block on
+    /// the signaling thread while that thread is flushing this queue.
+    ///
+    /// The candidate is converted before it is queued, so a string that
+    /// cannot be passed to the native side is reported here.
+    pub fn add(
+        &self,
+        pc: *const RawRTCPeerConnection,
+        candidate: &RTCIceCandidate,
+    ) -> Result<(), RTCError> {
+        let raw: RawRTCIceCandidate = candidate.try_into().map_err(RTCError::StringError)?;
+        if let Some(queue) = self.queue.lock().unwrap().as_mut() {
+            queue.push(raw);
+            return Ok(());
+        }
+
+        add_ice_candidate(pc, &raw)
+    }
+
+    /// Called from the set remote description callback once the description
+    /// has been applied, so the queue is flushed even if the future was
+    /// dropped. Adds every queued candidate to the connection, candidates
+    /// added after this go straight to the connection.
+    ///
+    /// A queued candidate the native agent rejects is dropped, there is no
+    /// caller left to report it to.
+    pub fn flush(&self, pc: *const RawRTCPeerConnection) {
+        let queued = self.queue.lock().unwrap().take();
+        for raw in queued.unwrap_or_default() {
+            let _ = add_ice_candidate(pc, &raw);
+        }
+    }
+}
+
+fn add_ice_candidate(
+    pc: *const RawRTCPeerConnection,
+    raw: &RawRTCIceCandidate,
+) -> Result<(), RTCError> {
+    if !unsafe { rtc_add_ice_candidate(pc, raw) } {
+        return Err(RTCError::AddIceCandidateFailed);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        let candidate = RTCIceCandidate::from_parts(
+            "candidate:842163049 1 udp 1677729535 203.0.113.1 3478 typ srflx",
+            Some("0".to_string()),
+            Some(0),
+        );
+
+        let json = serde_json::to_string(&candidate).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let relayed = RTCIceCandidate::from_parts(
+            value["candidate"].as_str().unwrap(),
+            value["sdp_mid"].as_str().map(str::to_string),
+            value["sdp_mline_index"].as_u64().map(|i| i as u16),
+        );
+        assert_eq!(relayed, candidate);
+        assert_eq!(
+            serde_json::from_str::<RTCIceCandidate>(&json).unwrap(),
+            candidate
+        );
+
+        let end_of_candidates = RTCIceCandidate::from_parts("", None, None);
+        let json = serde_json::to_string(&end_of_candidates).unwrap();
+        assert_eq!(
+            serde_json::from_str::<RTCIceCandidate>(&json).unwrap(),
+            end_of_candidates
+        );
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        for candidate in [
+            RTCIceCandidate::from_parts(
+                "candidate:1 1 udp 1 10.0.0.1 5000 typ host",
+                Some("audio".to_string()),
+                Some(1),
+            ),
+            RTCIceCandidate::from_parts("", None, None),
+        ] {
+            let raw: RawRTCIceCandidate = (&candidate).try_into().unwrap();
+            assert_eq!(RTCIceCandidate::try_from(&raw).unwrap(), candidate);
+        }
+    }
+}
@@ -2,20 +2,23 @@ use std::{
     error::Error,
     ffi::{c_char, c_int, c_void},
     fmt,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc, Mutex, PoisonError, RwLock, RwLockReadGuard,
+    },
 };
 
 use crate::{
     auto_ptr::HeapPointer,
     create_description_observer::{CreateDescriptionFuture, CreateDescriptionKind},
     cstr::{free_cstring, to_c_str, StringError},
-    observer::{ObserverRef, EVENTS},
+    observer::{CandidateFilter, ObserverRef, EVENTS},
     rtc_datachannel::RawDataChannelOptions,
-    rtc_icecandidate::RawRTCIceCandidate,
-    rtc_peerconnection_configure::RawRTCPeerConnectionConfigure,
+    rtc_icecandidate::PendingCandidates,
+    rtc_peerconnection_configure::{ConfigError, RawRTCPeerConnectionConfigure},
     set_description_observer::{SetDescriptionFuture, SetDescriptionKind},
     DataChannel, DataChannelOptions, MediaStream, MediaStreamTrack, Observer, RTCConfiguration,
-    RTCDataChannel, RTCIceCandidate, RTCSessionDescription,
+    RTCDataChannel, RTCIceCandidate, RTCSessionDescription, SignalingState,
 };
 
 #[allow(improper_ctypes)]
@@ -53,20 +56,63 @@ extern "C" {
 
 pub(crate) type RawRTCPeerConnection = c_void;
 
+/// Whether [`RTCPeerConnection::close`] has released the native connection.
+/// Every native call holds the read guard, and close takes the write guard,
+/// so the connection cannot be released during a call.
+pub(crate) type Closed = Arc<RwLock<bool>>;
+
+/// Keeps the native connection alive for a native call, `None` if it is
+/// closed or being closed.
+///
+/// This never waits: a pending close is treated as closed, so a call made
+/// from the signaling thread cannot deadlock with a close that is waiting
+/// for the signaling thread.
+pub(crate) fn hold_open(closed: &RwLock<bool>) -> Option<RwLockReadGuard<'_, bool>> {
+    closed.try_read().ok().filter(|closed| !**closed)
+}
+
 #[derive(Debug)]
 pub enum RTCError {
     CreateRTCFailed,
     AddTrackFailed(i32),
     AddIceCandidateFailed,
+    /// The indexes of the candidates [`RTCPeerConnection::add_ice_candidates`]
+    /// could not add, the others were added.
+    AddIceCandidatesFailed(Vec<usize>),
     RemoveTrackFailed(i32),
+    CreateDataChannelFailed,
+    /// Both `max_retransmit_time` and `max_retransmits` were set on the
+    /// data channel options.
+    InvalidDataChannelOptions,
+    /// The connection has been closed with [`RTCPeerConnection::close`].
+    Closed,
     StringError(StringError),
+    ConfigError(ConfigError),
 }
 
 impl Error for RTCError {}
 
 impl fmt::Display for RTCError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self)
+        match self {
+            Self::CreateRTCFailed => write!(f, "failed to create the peer connection"),
+            Self::AddTrackFailed(code) => write!(f, "failed to add the track, error {}", code),
+            Self::AddIceCandidateFailed => write!(f, "failed to add the ICE candidate"),
+            Self::AddIceCandidatesFailed(indexes) => {
+                write!(f, "failed to add the ICE candidates at {:?}", indexes)
+            }
+            Self::RemoveTrackFailed(code) => {
+                write!(f, "failed to remove the track, error {}", code)
+            }
+            Self::CreateDataChannelFailed => write!(f, "failed to create the data channel"),
+            Self::InvalidDataChannelOptions => write!(
+                f,
+                "max_retransmit_time and max_retransmits cannot both be set"
+            ),
+            Self::Closed => write!(f, "the peer connection is closed"),
+            Self::StringError(e) => write!(f, "invalid string: {}", e),
+            Self::ConfigError(e) => write!(f, "invalid configuration: {}", e),
+        }
     }
 }
 
@@ -77,6 +123,11 @@ impl fmt::Display for RTCError {
 /// the connection, and close the connection once it's no longer needed.
 pub struct RTCPeerConnection {
     raw: *const RawRTCPeerConnection,
+    closed: Closed,
+    signaling_state: Arc<AtomicI32>,
+    pending_candidates: Arc<PendingCandidates>,
+    configuration: RTCConfiguration,
+    candidate_filter: CandidateFilter,
     tracks: Mutex<Vec<(MediaStreamTrack, Arc<MediaStream>)>>,
     #[allow(dead_code)]
     observer: HeapPointer<ObserverRef>,
@@ -91,24 +142,34 @@ impl RTCPeerConnection {
     /// The RTCPeerConnection constructor returns a newly-created
     /// RTCPeerConnection, which represents a connection between the local
     /// device and a remote peer.
+    ///
+    /// The configuration is copied into the connection, so it can be dropped
+    /// or reused as soon as this returns.
     pub fn new<T: Observer + 'static>(
         config_: &RTCConfiguration,
         observer_: T,
     ) -> Result<Arc<Self>, RTCError> {
+        let raw_config = config_.try_get_raw().map_err(RTCError::ConfigError)?;
+
+        let observer_ = ObserverRef::new(observer_);
+        let signaling_state = observer_.signaling_state();
+        let candidate_filter = observer_.candidate_filter();
+
         let observer = HeapPointer::new();
         let config = HeapPointer::new();
         let raw = unsafe {
-            rtc_create_peer_connection(
-                config.set(config_.get_raw()),
-                &EVENTS,
-                observer.set(ObserverRef::new(observer_)),
-            )
+            rtc_create_peer_connection(config.set(raw_config), &EVENTS, observer.set(observer_))
         };
 
         if raw.is_null() {
             Err(RTCError::CreateRTCFailed)
         } else {
             Ok(Arc::new(Self {
+                closed: Arc::new(RwLock::new(false)),
+                signaling_state,
+                pending_candidates: Arc::new(PendingCandidates::new()),
+                configuration: config_.clone(),
+                candidate_filter,
                 tracks: Mutex::new(Vec::with_capacity(10)),
                 observer,
                 config,
@@ -126,7 +187,7 @@ impl RTCPeerConnection {
     /// signaling channel to a potential peer to request a connection or to
     /// update the configuration of an existing connection.
     pub fn create_offer(&self) -> CreateDescriptionFuture {
-        CreateDescriptionFuture::create(self.raw, CreateDescriptionKind::Offer)
+        CreateDescriptionFuture::create(self.raw, CreateDescriptionKind::Offer, self.closed.clone())
     }
 
     /// The create_answer() method on the RTCPeerConnection interface creates an
@@ -138,29 +199,69 @@ impl RTCPeerConnection {
     /// then be sent to the source of the offer to continue the negotiation
     /// process.
     pub fn create_answer(&self) -> CreateDescriptionFuture {
-        CreateDescriptionFuture::create(self.raw, CreateDescriptionKind::Answer)
+        CreateDescriptionFuture::create(
+            self.raw,
+            CreateDescriptionKind::Answer,
+            self.closed.clone(),
+        )
     }
 
     /// The RTCPeerConnection method setLocalDescription() changes the local
     /// description associated with the connection. This description specifies
     /// the properties of the local end of the connection, including the media
     /// format.
+    ///
+    /// The future fails without reaching the native connection if the SDP is
+    /// empty, or if the description type is not allowed in the current
+    /// signaling state, for example an answer while no remote offer is set.
     pub fn set_local_description<'b>(
         &'b self,
         desc: &'b RTCSessionDescription,
     ) -> SetDescriptionFuture<'b> {
-        SetDescriptionFuture::create(self.raw, desc, SetDescriptionKind::Local)
+        SetDescriptionFuture::create(
+            self.raw,
+            desc,
+            SetDescriptionKind::Local,
+            self.closed.clone(),
+            self.signaling_state.clone(),
+            self.pending_candidates.clone(),
+        )
     }
 
     /// The RTCPeerConnection method setRemoteDescription() sets the specified
     /// session description as the remote peer's current offer or answer. The
     /// description specifies the properties of the remote end of the
     /// connection, including the media format.
+    ///
+    /// The same checks as [`RTCPeerConnection::set_local_description`]
+    /// apply, so a remote answer is rejected unless a local offer is set.
+    ///
+    /// Once the description is applied, any remote candidates queued by
+    /// [`RTCPeerConnection::add_ice_candidate`] are added to the connection.
     pub fn set_remote_description<'b>(
         &'b self,
         desc: &'b RTCSessionDescription,
     ) -> SetDescriptionFuture<'b> {
-        SetDescriptionFuture::create(self.raw, desc, SetDescriptionKind::Remote)
+        SetDescriptionFuture::create(
+            self.raw,
+            desc,
+            SetDescriptionKind::Remote,
+            self.closed.clone(),
+            self.signaling_state.clone(),
+            self.pending_candidates.clone(),
+        )
+    }
+
+    /// The signalingState read-only property of the RTCPeerConnection
+    /// interface returns a string value describing the state of the signaling
+    /// process on the local end of the connection while connecting or
+    /// reconnecting to another peer.
+    pub fn signaling_state(&self) -> SignalingState {
+        if hold_open(&self.closed).is_none() {
+            SignalingState::Closed
+        } else {
+            SignalingState::from(self.signaling_state.load(Ordering::Acquire))
+        }
     }
 
     /// When a web site or app using RTCPeerConnection receives a new ICE
@@ -185,16 +286,55 @@ impl RTCPeerConnection {
     /// a list of potential connection methods. This is covered in more
     /// detail in the articles WebRTC connectivity and Signaling and video
     /// calling.
+    ///
+    /// Candidates that arrive before a remote description has been set are
+    /// queued and added once [`RTCPeerConnection::set_remote_description`]
+    /// completes, so trickled candidates can be passed in as they are
+    /// received.
     pub fn add_ice_candidate<'b>(&'b self, candidate: &'b RTCIceCandidate) -> Result<(), RTCError> {
-        let raw: RawRTCIceCandidate = candidate.try_into().map_err(|e| RTCError::StringError(e))?;
-        let ret = unsafe { rtc_add_ice_candidate(self.raw, &raw) };
-        if !ret {
-            return Err(RTCError::AddIceCandidateFailed);
+        let _open = hold_open(&self.closed).ok_or(RTCError::Closed)?;
+
+        self.pending_candidates.add(self.raw, candidate)
+    }
+
+    /// Adds a batch of remote candidates, as if each was passed to
+    /// [`RTCPeerConnection::add_ice_candidate`] in order.
+    ///
+    /// A candidate that fails does not stop the rest of the batch, the
+    /// indexes of all failed candidates are reported together.
+    /// Candidates are checked before they are queued, so one containing a
+    /// NUL byte is reported even before the remote description is set.
+    pub fn add_ice_candidates(&self, candidates: &[RTCIceCandidate]) -> Result<(), RTCError> {
+        let _open = hold_open(&self.closed).ok_or(RTCError::Closed)?;
+
+        let failed: Vec<usize> = candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| self.pending_candidates.add(self.raw, candidate).is_err())
+            .map(|(i, _)| i)
+            .collect();
+        if !failed.is_empty() {
+            return Err(RTCError::AddIceCandidatesFailed(failed));
         }
 
         Ok(())
     }
 
+    /// Sets a filter that is consulted for every locally gathered candidate
+    /// before [`Observer::on_ice_candidate`] is called, returning false drops
+    /// the candidate so it is never signaled. Replaces any previous filter.
+    ///
+    /// The filter runs on the native signaling thread and must not panic,
+    /// since the panic cannot unwind through the native caller. It only
+    /// affects trickled candidates, candidates already gathered into the
+    /// local description are not removed from its SDP.
+    pub fn set_candidate_filter<F>(&self, filter: F)
+    where
+        F: FnMut(&RTCIceCandidate) -> bool + Send + 'static,
+    {
+        *self.candidate_filter.lock().unwrap() = Some(Box::new(filter));
+    }
+
     /// The RTCPeerConnection method addTrack() adds a new media track to the
     /// set of tracks which will be transmitted to the other peer.
     pub fn add_track(
@@ -202,6 +342,8 @@ impl RTCPeerConnection {
         track: MediaStreamTrack,
         stream: Arc<MediaStream>,
     ) -> Result<(), RTCError> {
+        let _open = hold_open(&self.closed).ok_or(RTCError::Closed)?;
+
         let ret = unsafe { rtc_add_media_stream_track(self.raw, track.get_raw(), stream.get_id()) };
         if ret != 0 {
             return Err(RTCError::AddTrackFailed(ret));
@@ -223,6 +365,8 @@ impl RTCPeerConnection {
     /// negotiationneeded event is sent to the RTCPeerConnection to let the
     /// local end know this negotiation must occur.
     pub fn remove_track(&self, track: MediaStreamTrack) -> Result<(), RTCError> {
+        let _open = hold_open(&self.closed).ok_or(RTCError::Closed)?;
+
         let ret = unsafe { rtc_remove_media_stream_track(self.raw, track.get_raw()) };
         if ret != 0 {
             return Err(RTCError::RemoveTrackFailed(ret));
@@ -234,17 +378,96 @@ impl RTCPeerConnection {
     /// The createDataChannel() method on the RTCPeerConnection interface
     /// creates a new channel linked with the remote peer, over which any kind
     /// of data may be transmitted.
-    pub fn create_data_channel(&self, label: &str, opt: &DataChannelOptions) -> RTCDataChannel {
-        let c_label = to_c_str(label).unwrap();
-        let opt: RawDataChannelOptions = opt.into();
+    ///
+    /// Setting both `max_retransmit_time` and `max_retransmits` is not
+    /// allowed and fails with [`RTCError::InvalidDataChannelOptions`].
+    pub fn create_data_channel(
+        &self,
+        label: &str,
+        opt: &DataChannelOptions,
+    ) -> Result<RTCDataChannel, RTCError> {
+        let _open = hold_open(&self.closed).ok_or(RTCError::Closed)?;
+
+        if !opt.is_valid() {
+            return Err(RTCError::InvalidDataChannelOptions);
+        }
+
+        let opt: RawDataChannelOptions = opt.try_into().map_err(RTCError::StringError)?;
+        let c_label = to_c_str(label).map_err(RTCError::StringError)?;
         let raw = unsafe { rtc_create_data_channel(self.raw, c_label, &opt) };
         free_cstring(c_label);
-        DataChannel::from_raw(raw)
+        if raw.is_null() {
+            return Err(RTCError::CreateDataChannelFailed);
+        }
+
+        Ok(DataChannel::from_raw(raw))
+    }
+
+    /// The getConfiguration() method of the RTCPeerConnection interface
+    /// returns an object which indicates the current configuration of the
+    /// RTCPeerConnection on which the method is called.
+    ///
+    /// Fields left as `None` were not set, and the native library applies
+    /// its defaults for them: `balanced` bundling and a required RTCP mux.
+    pub fn current_configuration(&self) -> RTCConfiguration {
+        self.configuration.clone()
+    }
+
+    /// The close() method of the RTCPeerConnection interface closes the
+    /// current peer connection.
+    ///
+    /// Closing is final, no further media or data flows and the connection
+    /// cannot be reopened. Calling this more than once has no effect, and
+    /// the connection is closed automatically when it is dropped.
+    ///
+    /// Close waits for native calls made by other threads to return, and
+    /// those may be waiting for the signaling thread, so it must not be
+    /// called from an [`Observer`] callback.
+    pub fn close(&self) {
+        let mut closed = self.closed.write().unwrap_or_else(PoisonError::into_inner);
+        if !*closed {
+            *closed = true;
+            unsafe { rtc_close(self.raw) }
+        }
     }
 }
 
 impl Drop for RTCPeerConnection {
     fn drop(&mut self) {
-        unsafe { rtc_close(self.raw) }
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hold_open_fails_once_closing() {
+        let closed: Closed = Arc::new(RwLock::new(false));
+        {
+            let first = hold_open(&closed);
+            assert!(first.is_some());
+            assert!(hold_open(&closed).is_some());
+            assert!(closed.try_write().is_err());
+        }
+
+        let mut guard = closed.write().unwrap();
+        assert!(hold_open(&closed).is_none());
+        *guard = true;
+        drop(guard);
+        assert!(hold_open(&closed).is_none());
+    }
+
+    #[test]
+    fn errors_display_a_message() {
+        assert_eq!(
+            RTCError::Closed.to_string(),
+            "the peer connection is closed"
+        );
+        assert_eq!(
+            RTCError::ConfigError(ConfigError::StringError(StringError::NulError)).to_string(),
+            "invalid configuration: invalid string: the string contains a NUL byte"
+        );
     }
 }
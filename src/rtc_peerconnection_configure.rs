@@ -1,7 +1,16 @@
-use std::ffi::{c_char, c_int};
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    ffi::{c_char, c_int},
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::auto_ptr::ArrayExt;
-use crate::cstr::{free_cstring, to_c_str};
+use crate::cstr::{free_cstring, to_c_str, StringError};
 
 /// How to handle negotiation of candidates when remote peer is not compatible
 /// with standard SDP BUNDLE.
@@ -17,7 +26,8 @@ use crate::cstr::{free_cstring, to_c_str};
 /// across a single 5-tuple; that is, from a single IP and port on one peer to a
 /// single IP and port on the other peer, using the same transport protocol.
 #[repr(i32)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum BundlePolicy {
     /// The ICE agent initially creates one RTCDtlsTransport for each type of
     /// content added: audio, video, and data channels. If the remote endpoint
@@ -38,9 +48,14 @@ pub enum BundlePolicy {
 
 /// The current ICE transport policy; if the policy isn't specified, all is
 /// assumed by default, allowing all candidates to be considered.
+///
+/// Note that `None` is an explicit policy and is different from leaving the
+/// policy unset in [`RTCConfiguration`].
 #[repr(i32)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum IceTransportPolicy {
+    /// No ICE candidates will be gathered or considered.
     None = 1,
     /// Only ICE candidates whose IP addresses are being relayed, such as those
     /// being passed through a STUN or TURN server, will be considered.
@@ -54,7 +69,8 @@ pub enum IceTransportPolicy {
 /// The RTCP mux policy to use when gathering ICE candidates,
 /// in order to support non-multiplexed RTCP.
 #[repr(i32)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum RtcpMuxPolicy {
     /// Instructs the ICE agent to gather both RTP and RTCP candidates.
     /// If the remote peer can multiplex RTCP,
@@ -68,6 +84,100 @@ pub enum RtcpMuxPolicy {
     Require,
 }
 
+/// Returned when parsing a policy from a string that is not one of its
+/// WebRTC spellings.
+#[derive(Debug)]
+pub struct ParsePolicyError {
+    policy: &'static str,
+    value: String,
+}
+
+impl Error for ParsePolicyError {}
+
+impl fmt::Display for ParsePolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown {} \"{}\"", self.policy, self.value)
+    }
+}
+
+impl ParsePolicyError {
+    fn new(policy: &'static str, value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            policy,
+        }
+    }
+}
+
+impl fmt::Display for BundlePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Balanced => "balanced",
+            Self::MaxCompat => "max-compat",
+            Self::MaxBundle => "max-bundle",
+        })
+    }
+}
+
+impl FromStr for BundlePolicy {
+    type Err = ParsePolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "balanced" => Ok(Self::Balanced),
+            "max-compat" => Ok(Self::MaxCompat),
+            "max-bundle" => Ok(Self::MaxBundle),
+            _ => Err(ParsePolicyError::new("bundle policy", s)),
+        }
+    }
+}
+
+impl fmt::Display for IceTransportPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Relay => "relay",
+            Self::Public => "public",
+            Self::All => "all",
+        })
+    }
+}
+
+impl FromStr for IceTransportPolicy {
+    type Err = ParsePolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "relay" => Ok(Self::Relay),
+            "public" => Ok(Self::Public),
+            "all" => Ok(Self::All),
+            _ => Err(ParsePolicyError::new("ICE transport policy", s)),
+        }
+    }
+}
+
+impl fmt::Display for RtcpMuxPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Negotiate => "negotiate",
+            Self::Require => "require",
+        })
+    }
+}
+
+impl FromStr for RtcpMuxPolicy {
+    type Err = ParsePolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "negotiate" => Ok(Self::Negotiate),
+            "require" => Ok(Self::Require),
+            _ => Err(ParsePolicyError::new("RTCP mux policy", s)),
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub(crate) struct RawRTCIceServer {
@@ -84,6 +194,7 @@ impl Drop for RawRTCIceServer {
         free_cstring(self.username.cast_mut());
         unsafe {
             if !self.urls.is_null() {
+                debug_assert!(0 <= self.urls_size && self.urls_size <= self.urls_capacity);
                 for url in Vec::from_raw_parts(
                     self.urls.cast_mut(),
                     self.urls_size as usize,
@@ -96,6 +207,13 @@ impl Drop for RawRTCIceServer {
     }
 }
 
+/// The policy fields are the enum discriminants passed through verbatim.
+/// Every policy enum starts at 1, so 0 is reserved to mean "unset, use the
+/// library default" and never collides with a real variant.
+///
+/// `ice_servers` and the `urls` of each server come from
+/// [`ArrayExt::into_c_layout`], their size and capacity fields must hold
+/// exactly what it returned since Drop frees the arrays with them.
 #[repr(C)]
 #[derive(Debug)]
 pub(crate) struct RawRTCPeerConnectionConfigure {
@@ -114,6 +232,10 @@ impl Drop for RawRTCPeerConnectionConfigure {
         unsafe {
             free_cstring(self.peer_identity.cast_mut());
             if !self.ice_servers.is_null() {
+                debug_assert!(
+                    0 <= self.ice_servers_size
+                        && self.ice_servers_size <= self.ice_servers_capacity
+                );
                 let _ = Vec::from_raw_parts(
                     self.ice_servers.cast_mut(),
                     self.ice_servers_size as usize,
@@ -124,6 +246,161 @@ impl Drop for RawRTCPeerConnectionConfigure {
     }
 }
 
+/// Reasons an [`RTCIceServer`] is rejected by [`RTCIceServer::validate`].
+#[derive(Debug)]
+pub enum IceServerError {
+    /// The server does not specify any URL.
+    MissingUrls,
+    /// The URL does not use one of the `stun:`, `stuns:`, `turn:` or `turns:`
+    /// schemes, compared case insensitively.
+    InvalidUrl(String),
+    /// A TURN URL was given without both a username and a credential.
+    MissingCredential(String),
+    /// The URL has a `transport` parameter that is unknown or not allowed
+    /// for its scheme, such as `stun:...?transport=tcp` or
+    /// `turns:...?transport=udp`.
+    InvalidTransport(String),
+    StringError(StringError),
+}
+
+impl Error for IceServerError {}
+
+impl fmt::Display for IceServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingUrls => write!(f, "the ICE server has no urls"),
+            Self::InvalidUrl(url) => write!(f, "\"{}\" is not a STUN or TURN url", url),
+            Self::MissingCredential(url) => {
+                write!(f, "\"{}\" needs a username and a credential", url)
+            }
+            Self::InvalidTransport(url) => {
+                write!(f, "\"{}\" has an unsupported transport", url)
+            }
+            Self::StringError(e) => write!(f, "invalid string: {}", e),
+        }
+    }
+}
+
+/// Errors that prevent an [`RTCConfiguration`] from being handed to the
+/// native library.
+#[derive(Debug)]
+pub enum ConfigError {
+    IceServerError(IceServerError),
+    StringError(StringError),
+}
+
+impl Error for ConfigError {}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IceServerError(e) => write!(f, "invalid ICE server: {}", e),
+            Self::StringError(e) => write!(f, "invalid string: {}", e),
+        }
+    }
+}
+
+/// The transport used to reach an ICE server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IceTransport {
+    Udp,
+    Tcp,
+    /// TLS over TCP, selected by the `stuns:` and `turns:` schemes.
+    Tls,
+}
+
+impl IceTransport {
+    /// Derives the transport from a STUN or TURN URL: secure schemes use
+    /// TLS, otherwise the `transport` query parameter is honored and UDP
+    /// is the default.
+    ///
+    /// `turns:...?transport=udp` would be DTLS, which is not modelled here,
+    /// so it reports UDP. [`RTCIceServer::validate`] rejects such URLs.
+    pub fn from_url(url: &str) -> Self {
+        let secure = matches!(scheme(url).as_deref(), Some("stuns" | "turns"));
+        match transport_param(url) {
+            Some("udp") => Self::Udp,
+            _ if secure => Self::Tls,
+            Some("tcp") => Self::Tcp,
+            _ => Self::Udp,
+        }
+    }
+}
+
+/// Returns the scheme of a URL in lower case, since URI schemes are case
+/// insensitive.
+fn scheme(url: &str) -> Option<String> {
+    url.split_once(':')
+        .map(|(scheme, _)| scheme.to_ascii_lowercase())
+}
+
+/// Returns the value of the `transport` query parameter of a URL, if any.
+fn transport_param(url: &str) -> Option<&str> {
+    url.split_once('?')?
+        .1
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("transport="))
+}
+
+/// One or more ICE server URLs.
+///
+/// Mirrors the WebRTC API, where `urls` may be either a single string or an
+/// array of strings. Both forms are stored as a list.
+#[derive(Default, Clone, Debug)]
+pub struct IceUrls(pub Vec<String>);
+
+impl From<&str> for IceUrls {
+    fn from(url: &str) -> Self {
+        Self(vec![url.to_string()])
+    }
+}
+
+impl From<String> for IceUrls {
+    fn from(url: String) -> Self {
+        Self(vec![url])
+    }
+}
+
+impl From<Vec<String>> for IceUrls {
+    fn from(urls: Vec<String>) -> Self {
+        Self(urls)
+    }
+}
+
+impl<S: AsRef<str>> From<&[S]> for IceUrls {
+    fn from(urls: &[S]) -> Self {
+        Self(urls.iter().map(|s| s.as_ref().to_string()).collect())
+    }
+}
+
+impl From<IceUrls> for Vec<String> {
+    fn from(urls: IceUrls) -> Self {
+        urls.0
+    }
+}
+
+impl<'de> Deserialize<'de> for IceUrls {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Urls {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        Ok(match Urls::deserialize(deserializer)? {
+            Urls::One(url) => url.into(),
+            Urls::Many(urls) => urls.into(),
+        })
+    }
+}
+
+fn deserialize_urls<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error> {
+    Ok(Option::<IceUrls>::deserialize(deserializer)?.map(Into::into))
+}
+
 /// The RTCIceServer dictionary defines how to connect to a single ICE
 /// server (such as a STUN or TURN server).
 ///
@@ -131,7 +408,7 @@ impl Drop for RawRTCPeerConnectionConfigure {
 /// used by the ICE agent; these are typically STUN and/or TURN servers.
 /// If this isn't specified, the connection attempt will be made with no STUN or
 /// TURN server available, which limits the connection to local peers.
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct RTCIceServer {
     /// The credential to use when logging into the server.
     /// This is only used if the RTCIceServer represents a TURN server.
@@ -142,44 +419,190 @@ pub struct RTCIceServer {
     /// This required property is either a single string or an array of
     /// strings, each specifying a URL which can be used to connect to the
     /// server.
+    #[serde(default, deserialize_with = "deserialize_urls")]
     pub urls: Option<Vec<String>>,
 }
 
-impl Into<RawRTCIceServer> for &RTCIceServer {
-    fn into(self) -> RawRTCIceServer {
-        let (urls, urls_size, urls_capacity) = self
-            .urls
-            .as_ref()
-            .map(|v| {
-                v.iter()
-                    .map(|s| to_c_str(s).unwrap())
-                    .collect::<Vec<*const c_char>>()
-                    .into_c_layout()
-            })
-            .unwrap_or((std::ptr::null_mut(), 0, 0));
-        RawRTCIceServer {
-            credential: self
-                .credential
-                .as_ref()
-                .map(|s| to_c_str(s).unwrap())
-                .unwrap_or(std::ptr::null_mut()),
-            username: self
-                .username
-                .as_ref()
-                .map(|s| to_c_str(s).unwrap())
-                .unwrap_or(std::ptr::null_mut()),
-            urls_capacity: urls_capacity as c_int,
-            urls_size: urls_size as c_int,
-            urls,
+impl RTCIceServer {
+    /// Creates a STUN server from a single URL or a list of URLs.
+    ///
+    /// ```no_run
+    /// use librtc::RTCIceServer;
+    ///
+    /// let server = RTCIceServer::stun("stun:stun.l.google.com:19302");
+    /// let server = RTCIceServer::stun(&["stun:a.example.com", "stun:b.example.com"][..]);
+    /// ```
+    pub fn stun(urls: impl Into<IceUrls>) -> Self {
+        Self::with_urls(urls)
+    }
+
+    /// Creates a TURN server from a single URL or a list of URLs and the
+    /// credentials used to authenticate with it.
+    ///
+    /// ```no_run
+    /// use librtc::RTCIceServer;
+    ///
+    /// let server = RTCIceServer::turn("turn:turn.example.com:3478", "user", "pass");
+    /// ```
+    pub fn turn(
+        urls: impl Into<IceUrls>,
+        username: impl Into<String>,
+        credential: impl Into<String>,
+    ) -> Self {
+        Self {
+            credential: Some(credential.into()),
+            username: Some(username.into()),
+            urls: Some(urls.into().into()),
         }
     }
+
+    /// Creates a server reachable through any of the given URLs, without
+    /// credentials.
+    pub fn with_urls(urls: impl Into<IceUrls>) -> Self {
+        Self {
+            urls: Some(urls.into().into()),
+            ..Default::default()
+        }
+    }
+
+    /// Checks that every URL uses a STUN or TURN scheme, that no string
+    /// contains an interior NUL byte, and that TURN servers carry both a
+    /// username and a credential.
+    pub fn validate(&self) -> Result<(), IceServerError> {
+        let urls = match &self.urls {
+            Some(urls) if !urls.is_empty() => urls,
+            _ => return Err(IceServerError::MissingUrls),
+        };
+
+        for field in [&self.username, &self.credential].into_iter().flatten() {
+            if field.contains('\0') {
+                return Err(IceServerError::StringError(StringError::NulError));
+            }
+        }
+
+        for url in urls {
+            if url.contains('\0') {
+                return Err(IceServerError::StringError(StringError::NulError));
+            }
+
+            match scheme(url).as_deref() {
+                Some(scheme @ ("turn" | "turns")) => {
+                    if self.username.is_none() || self.credential.is_none() {
+                        return Err(IceServerError::MissingCredential(url.clone()));
+                    }
+
+                    // TURN over DTLS is not supported.
+                    let allowed = match transport_param(url) {
+                        None | Some("tcp") => true,
+                        Some("udp") => scheme == "turn",
+                        _ => false,
+                    };
+
+                    if !allowed {
+                        return Err(IceServerError::InvalidTransport(url.clone()));
+                    }
+                }
+                Some("stun" | "stuns") => {
+                    // RFC 7064 STUN URIs have no query part, the transport is
+                    // implied by the scheme.
+                    if transport_param(url).is_some() {
+                        return Err(IceServerError::InvalidTransport(url.clone()));
+                    }
+                }
+                _ => return Err(IceServerError::InvalidUrl(url.clone())),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The transport used to reach this server, derived from its first URL.
+    /// A server without URLs reports UDP.
+    pub fn transport(&self) -> IceTransport {
+        self.urls
+            .iter()
+            .flatten()
+            .next()
+            .map(|url| IceTransport::from_url(url))
+            .unwrap_or(IceTransport::Udp)
+    }
+
+    /// The URLs as a set, so order and duplicates don't affect equality.
+    fn url_set(&self) -> BTreeSet<&str> {
+        self.urls.iter().flatten().map(String::as_str).collect()
+    }
+}
+
+/// Two servers are equal if they have the same credentials and the same URLs,
+/// in any order. This allows deduplicating merged server lists with a
+/// `HashSet`.
+impl PartialEq for RTCIceServer {
+    fn eq(&self, other: &Self) -> bool {
+        self.username == other.username
+            && self.credential == other.credential
+            && self.url_set() == other.url_set()
+    }
+}
+
+impl Eq for RTCIceServer {}
+
+impl Hash for RTCIceServer {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.username.hash(state);
+        self.credential.hash(state);
+        self.url_set().hash(state);
+    }
+}
+
+impl TryInto<RawRTCIceServer> for &RTCIceServer {
+    type Error = StringError;
+
+    fn try_into(self) -> Result<RawRTCIceServer, Self::Error> {
+        // Fill the raw server in place, so that if a later string fails to
+        // convert, dropping it frees everything converted so far.
+        let mut raw = RawRTCIceServer {
+            credential: std::ptr::null(),
+            username: std::ptr::null(),
+            urls: std::ptr::null(),
+            urls_capacity: 0,
+            urls_size: 0,
+        };
+
+        if let Some(credential) = &self.credential {
+            raw.credential = to_c_str(credential)?;
+        }
+
+        if let Some(username) = &self.username {
+            raw.username = to_c_str(username)?;
+        }
+
+        if let Some(urls) = &self.urls {
+            let mut c_urls = Vec::with_capacity(urls.len());
+            for url in urls {
+                match to_c_str(url) {
+                    Ok(c_url) => c_urls.push(c_url),
+                    Err(e) => {
+                        c_urls.into_iter().for_each(free_cstring);
+                        return Err(e);
+                    }
+                }
+            }
+
+            let (urls, urls_size, urls_capacity) = c_urls.into_c_layout();
+            raw.urls_capacity = urls_capacity as c_int;
+            raw.urls_size = urls_size as c_int;
+            raw.urls = urls;
+        }
+
+        Ok(raw)
+    }
 }
 
 /// RTCPeerConnection Configuration.
 ///
 /// The RTCPeerConnection is a newly-created RTCPeerConnection,
 /// which represents a connection between the local device and a remote peer.
-#[derive(Default, Debug)]
+#[derive(Default, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RTCConfiguration {
     /// Specifies how to handle negotiation of candidates when the remote peer
     /// is not compatible with the SDP BUNDLE standard. If the remote endpoint
@@ -209,52 +632,638 @@ pub struct RTCConfiguration {
     /// be used by the ICE agent; these are typically STUN and/or TURN servers.
     /// If this isn't specified, the connection attempt will be made with no
     /// STUN or TURN server available, which limits the connection to local
-    /// peers.
+    /// peers. An empty list is treated the same as `None`.
     pub ice_servers: Option<Vec<RTCIceServer>>,
-    /// An unsigned 16-bit integer value which specifies the size of the
+    /// An unsigned 8-bit integer value which specifies the size of the
     /// prefetched ICE candidate pool.
     /// The default value is 0 (meaning no candidate prefetching will occur).
     /// You may find in some cases that connections can be established more
     /// quickly by allowing the ICE agent to start fetching ICE candidates
     /// before you start trying to connect, so that they're already available
     /// for inspection when RTCPeerConnection.setLocalDescription() is called.
+    ///
+    /// Prefetching is not free: every pooled candidate holds a socket open
+    /// and, for STUN or TURN servers, costs a binding or an allocation on the
+    /// server, whether or not it is ever used. Keep the pool small.
     pub ice_candidate_pool_size: Option<u8>,
 }
 
 unsafe impl Send for RTCConfiguration {}
 unsafe impl Sync for RTCConfiguration {}
 
-impl Into<RawRTCPeerConnectionConfigure> for &RTCConfiguration {
-    fn into(self) -> RawRTCPeerConnectionConfigure {
-        let (ice_servers, ice_servers_size, ice_servers_capacity) = self
-            .ice_servers
-            .as_ref()
-            .map(|i| {
-                i.iter()
-                    .map(|s| s.into())
-                    .collect::<Vec<RawRTCIceServer>>()
-                    .into_c_layout()
-            })
-            .unwrap_or((std::ptr::null_mut(), 0, 0));
-        RawRTCPeerConnectionConfigure {
+impl TryInto<RawRTCPeerConnectionConfigure> for &RTCConfiguration {
+    type Error = ConfigError;
+
+    fn try_into(self) -> Result<RawRTCPeerConnectionConfigure, Self::Error> {
+        let mut raw = RawRTCPeerConnectionConfigure {
             bundle_policy: self.bundle_policy.map(|i| i as c_int).unwrap_or(0),
             ice_transport_policy: self.ice_transport_policy.map(|i| i as c_int).unwrap_or(0),
-            peer_identity: self
-                .peer_identity
-                .as_ref()
-                .map(|s| to_c_str(s).unwrap())
-                .unwrap_or(std::ptr::null_mut()),
+            peer_identity: std::ptr::null(),
             rtcp_mux_policy: self.rtcp_mux_policy.map(|i| i as c_int).unwrap_or(0),
-            ice_candidate_pool_size: self.ice_candidate_pool_size.unwrap_or(0) as c_int,
-            ice_servers_capacity: ice_servers_capacity as c_int,
-            ice_servers_size: ice_servers_size as c_int,
-            ice_servers,
+            ice_candidate_pool_size: c_int::from(self.ice_candidate_pool_size.unwrap_or(0)),
+            ice_servers: std::ptr::null(),
+            ice_servers_capacity: 0,
+            ice_servers_size: 0,
+        };
+
+        if let Some(peer_identity) = &self.peer_identity {
+            raw.peer_identity = to_c_str(peer_identity).map_err(ConfigError::StringError)?;
         }
+
+        // An empty list is passed as null, exactly like an unset one.
+        if let Some(ice_servers) = self.ice_servers.as_ref().filter(|s| !s.is_empty()) {
+            let (ice_servers, ice_servers_size, ice_servers_capacity) = ice_servers
+                .iter()
+                .map(|s| s.try_into())
+                .collect::<Result<Vec<RawRTCIceServer>, _>>()
+                .map_err(ConfigError::StringError)?
+                .into_c_layout();
+            raw.ice_servers_capacity = ice_servers_capacity as c_int;
+            raw.ice_servers_size = ice_servers_size as c_int;
+            raw.ice_servers = ice_servers;
+        }
+
+        Ok(raw)
     }
 }
 
 impl RTCConfiguration {
-    pub(crate) fn get_raw(&self) -> RawRTCPeerConnectionConfigure {
-        self.into()
+    /// The configured ICE servers, an empty slice if none are set.
+    pub fn ice_servers(&self) -> &[RTCIceServer] {
+        self.ice_servers.as_deref().unwrap_or_default()
+    }
+
+    /// Validates every configured ICE server, see [`RTCIceServer::validate`].
+    pub fn validate(&self) -> Result<(), IceServerError> {
+        for server in self.ice_servers() {
+            server.validate()?;
+        }
+
+        Ok(())
+    }
+
+    /// Creates a builder for constructing the configuration with chained
+    /// calls instead of a struct literal.
+    pub fn builder() -> RTCConfigurationBuilder {
+        RTCConfigurationBuilder::default()
+    }
+
+    /// Validates the configuration and converts it into the layout expected
+    /// by the native library.
+    pub(crate) fn try_get_raw(&self) -> Result<RawRTCPeerConnectionConfigure, ConfigError> {
+        self.validate().map_err(ConfigError::IceServerError)?;
+        self.try_into()
+    }
+}
+
+impl AsRef<[RTCIceServer]> for RTCConfiguration {
+    fn as_ref(&self) -> &[RTCIceServer] {
+        self.ice_servers()
+    }
+}
+
+impl<'a> IntoIterator for &'a RTCConfiguration {
+    type Item = &'a RTCIceServer;
+    type IntoIter = std::slice::Iter<'a, RTCIceServer>;
+
+    /// Iterates over the configured ICE servers.
+    fn into_iter(self) -> Self::IntoIter {
+        self.ice_servers().iter()
+    }
+}
+
+/// A fluent builder for [`RTCConfiguration`].
+///
+/// ```no_run
+/// use librtc::{BundlePolicy, RTCConfiguration, RTCIceServer};
+///
+/// let config = RTCConfiguration::builder()
+///     .bundle_policy(BundlePolicy::MaxBundle)
+///     .add_ice_server(RTCIceServer {
+///         urls: Some(vec!["stun:stun.l.google.com:19302".to_string()]),
+///         ..Default::default()
+///     })
+///     .ice_candidate_pool_size(4)
+///     .build();
+/// ```
+#[derive(Default, Debug)]
+pub struct RTCConfigurationBuilder {
+    config: RTCConfiguration,
+}
+
+impl RTCConfigurationBuilder {
+    /// Sets how to handle negotiation of candidates when the remote peer is
+    /// not compatible with the SDP BUNDLE standard.
+    pub fn bundle_policy(mut self, policy: BundlePolicy) -> Self {
+        self.config.bundle_policy = Some(policy);
+        self
+    }
+
+    /// Sets the ICE transport policy.
+    pub fn ice_transport_policy(mut self, policy: IceTransportPolicy) -> Self {
+        self.config.ice_transport_policy = Some(policy);
+        self
+    }
+
+    /// Sets the target peer identity for the RTCPeerConnection.
+    pub fn peer_identity(mut self, identity: impl Into<String>) -> Self {
+        self.config.peer_identity = Some(identity.into());
+        self
+    }
+
+    /// Sets the RTCP mux policy to use when gathering ICE candidates.
+    pub fn rtcp_mux_policy(mut self, policy: RtcpMuxPolicy) -> Self {
+        self.config.rtcp_mux_policy = Some(policy);
+        self
+    }
+
+    /// Appends an ICE server to the list of servers used by the ICE agent.
+    pub fn add_ice_server(mut self, server: RTCIceServer) -> Self {
+        self.config
+            .ice_servers
+            .get_or_insert_with(Vec::new)
+            .push(server);
+        self
+    }
+
+    /// Replaces the list of ICE servers used by the ICE agent.
+    pub fn ice_servers(mut self, servers: Vec<RTCIceServer>) -> Self {
+        self.config.ice_servers = Some(servers);
+        self
+    }
+
+    /// Sets the size of the prefetched ICE candidate pool, see
+    /// [`RTCConfiguration::ice_candidate_pool_size`] for the cost of
+    /// prefetching.
+    pub fn ice_candidate_pool_size(mut self, size: u8) -> Self {
+        self.config.ice_candidate_pool_size = Some(size);
+        self
+    }
+
+    /// Consumes the builder and returns the configuration.
+    pub fn build(self) -> RTCConfiguration {
+        self.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cstr::from_c_str, test_alloc::outstanding};
+
+    fn raw_urls(raw: &RawRTCIceServer) -> Vec<String> {
+        (0..raw.urls_size as usize)
+            .map(|i| from_c_str(unsafe { *raw.urls.add(i) }).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn single_and_list_urls_convert_alike() {
+        let url = "stun:stun.l.google.com:19302";
+        let servers = [
+            RTCIceServer::stun(url),
+            RTCIceServer::stun(url.to_string()),
+            RTCIceServer::stun(&[url][..]),
+            RTCIceServer::stun(vec![url.to_string()]),
+        ];
+
+        for server in &servers {
+            let raw: RawRTCIceServer = server.try_into().unwrap();
+            assert_eq!(raw_urls(&raw), [url]);
+            assert!(raw.username.is_null());
+            assert!(raw.credential.is_null());
+        }
+    }
+
+    #[test]
+    fn url_list_keeps_order() {
+        let urls = ["turn:a.example.com", "turn:b.example.com?transport=tcp"];
+        let server = RTCIceServer::turn(&urls[..], "user", "pass");
+        let raw: RawRTCIceServer = (&server).try_into().unwrap();
+        assert_eq!(raw_urls(&raw), urls);
+        assert_eq!(from_c_str(raw.username).unwrap(), "user");
+        assert_eq!(from_c_str(raw.credential).unwrap(), "pass");
+    }
+
+    #[test]
+    fn policies_round_trip_through_strings() {
+        for policy in [
+            BundlePolicy::Balanced,
+            BundlePolicy::MaxCompat,
+            BundlePolicy::MaxBundle,
+        ] {
+            let parsed: BundlePolicy = policy.to_string().parse().unwrap();
+            assert_eq!(parsed as i32, policy as i32);
+        }
+
+        for policy in [
+            IceTransportPolicy::None,
+            IceTransportPolicy::Relay,
+            IceTransportPolicy::Public,
+            IceTransportPolicy::All,
+        ] {
+            let parsed: IceTransportPolicy = policy.to_string().parse().unwrap();
+            assert_eq!(parsed as i32, policy as i32);
+        }
+
+        for policy in [RtcpMuxPolicy::Negotiate, RtcpMuxPolicy::Require] {
+            let parsed: RtcpMuxPolicy = policy.to_string().parse().unwrap();
+            assert_eq!(parsed as i32, policy as i32);
+        }
+    }
+
+    #[test]
+    fn unknown_policy_is_rejected() {
+        let err = "max_bundle".parse::<BundlePolicy>().unwrap_err();
+        assert_eq!(err.to_string(), "unknown bundle policy \"max_bundle\"");
+        assert!("Relay".parse::<IceTransportPolicy>().is_err());
+        assert!("".parse::<RtcpMuxPolicy>().is_err());
+    }
+
+    #[test]
+    fn nul_in_url_is_an_error() {
+        let server = RTCIceServer::stun(&["stun:a.example.com", "stun:b\0.example.com"][..]);
+        let raw: Result<RawRTCIceServer, _> = (&server).try_into();
+        assert!(matches!(raw, Err(StringError::NulError)));
+
+        let config = RTCConfiguration::builder().add_ice_server(server).build();
+        assert!(matches!(
+            config.try_get_raw(),
+            Err(ConfigError::IceServerError(IceServerError::StringError(
+                StringError::NulError
+            )))
+        ));
+
+        let raw: Result<RawRTCPeerConnectionConfigure, _> = (&config).try_into();
+        assert!(matches!(
+            raw,
+            Err(ConfigError::StringError(StringError::NulError))
+        ));
+    }
+
+    fn assert_raw_server_frees(server: &RTCIceServer) {
+        let leaked = outstanding(|| {
+            let raw: RawRTCIceServer = server.try_into().unwrap();
+            drop(raw);
+        });
+        assert_eq!(leaked, (0, 0), "{:?}", server);
+    }
+
+    fn assert_raw_config_frees(config: &RTCConfiguration) {
+        let leaked = outstanding(|| {
+            let raw: RawRTCPeerConnectionConfigure = config.try_into().unwrap();
+            drop(raw);
+        });
+        assert_eq!(leaked, (0, 0), "{:?}", config);
+    }
+
+    #[test]
+    fn raw_server_drop_frees_everything() {
+        assert_raw_server_frees(&RTCIceServer::default());
+        assert_raw_server_frees(&RTCIceServer::with_urls(Vec::new()));
+        assert_raw_server_frees(&RTCIceServer::stun("stun:a.example.com"));
+        assert_raw_server_frees(&RTCIceServer::turn(
+            &["turn:a.example.com", "turns:b.example.com"][..],
+            "user",
+            "pass",
+        ));
+    }
+
+    #[test]
+    fn raw_config_drop_frees_everything() {
+        assert_raw_config_frees(&RTCConfiguration::default());
+        assert_raw_config_frees(&RTCConfiguration {
+            ice_servers: Some(Vec::new()),
+            ..Default::default()
+        });
+        assert_raw_config_frees(
+            &RTCConfiguration::builder()
+                .bundle_policy(BundlePolicy::MaxBundle)
+                .peer_identity("peer")
+                .add_ice_server(RTCIceServer::stun("stun:a.example.com"))
+                .add_ice_server(RTCIceServer::turn(
+                    &["turn:b.example.com", "turn:c.example.com?transport=tcp"][..],
+                    "user",
+                    "pass",
+                ))
+                .build(),
+        );
+    }
+
+    #[test]
+    fn failed_conversion_frees_everything() {
+        let server = RTCIceServer::turn(
+            &["turn:a.example.com", "turn:b\0.example.com"][..],
+            "user",
+            "pass",
+        );
+        let leaked = outstanding(|| {
+            let raw: Result<RawRTCIceServer, _> = (&server).try_into();
+            assert!(raw.is_err());
+        });
+        assert_eq!(leaked, (0, 0));
+
+        let config = RTCConfiguration::builder()
+            .peer_identity("peer")
+            .add_ice_server(RTCIceServer::stun("stun:a.example.com"))
+            .add_ice_server(server)
+            .build();
+        let leaked = outstanding(|| {
+            let raw: Result<RawRTCPeerConnectionConfigure, _> = (&config).try_into();
+            assert!(raw.is_err());
+        });
+        assert_eq!(leaked, (0, 0));
+    }
+
+    #[test]
+    fn transport_follows_scheme_and_parameter() {
+        let cases = [
+            ("stun:a.example.com", IceTransport::Udp),
+            ("stuns:a.example.com", IceTransport::Tls),
+            ("turn:a.example.com", IceTransport::Udp),
+            ("turn:a.example.com?transport=udp", IceTransport::Udp),
+            ("turn:a.example.com?transport=tcp", IceTransport::Tcp),
+            ("turns:a.example.com", IceTransport::Tls),
+            ("turns:a.example.com?transport=tcp", IceTransport::Tls),
+            ("turns:a.example.com?transport=udp", IceTransport::Udp),
+            ("TURNS:a.example.com", IceTransport::Tls),
+            ("Stuns:a.example.com", IceTransport::Tls),
+        ];
+
+        for (url, transport) in cases {
+            assert_eq!(IceTransport::from_url(url), transport, "{}", url);
+            let server = RTCIceServer::turn(url, "user", "pass");
+            assert_eq!(server.transport(), transport, "{}", url);
+        }
+    }
+
+    #[test]
+    fn validate_checks_scheme_and_transport() {
+        let valid = [
+            "stun:a.example.com",
+            "stuns:a.example.com",
+            "STUN:a.example.com",
+            "turn:a.example.com",
+            "turn:a.example.com?transport=udp",
+            "turn:a.example.com?transport=tcp",
+            "turns:a.example.com",
+            "turns:a.example.com?transport=tcp",
+            "Turn:a.example.com",
+        ];
+
+        for url in valid {
+            let server = RTCIceServer::turn(url, "user", "pass");
+            assert!(server.validate().is_ok(), "{}", url);
+        }
+
+        let invalid_transport = [
+            "stun:a.example.com?transport=udp",
+            "stun:a.example.com?transport=tcp",
+            "stuns:a.example.com?transport=tcp",
+            "turn:a.example.com?transport=tls",
+            "turns:a.example.com?transport=udp",
+        ];
+
+        for url in invalid_transport {
+            let server = RTCIceServer::turn(url, "user", "pass");
+            assert!(
+                matches!(server.validate(), Err(IceServerError::InvalidTransport(_))),
+                "{}",
+                url
+            );
+        }
+
+        assert!(matches!(
+            RTCIceServer::stun("http://a.example.com").validate(),
+            Err(IceServerError::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            RTCIceServer::with_urls("TURN:a.example.com").validate(),
+            Err(IceServerError::MissingCredential(_))
+        ));
+    }
+
+    #[test]
+    fn url_order_does_not_affect_equality() {
+        use std::collections::HashSet;
+
+        let a = RTCIceServer::turn(
+            &["turn:a.example.com", "turn:b.example.com"][..],
+            "user",
+            "pass",
+        );
+        let b = RTCIceServer::turn(
+            &["turn:b.example.com", "turn:a.example.com"][..],
+            "user",
+            "pass",
+        );
+        assert_eq!(a, b);
+
+        let other_credential = RTCIceServer::turn(
+            &["turn:a.example.com", "turn:b.example.com"][..],
+            "user",
+            "other",
+        );
+        assert_ne!(a, other_credential);
+
+        let servers: HashSet<_> = [a, b, other_credential].into_iter().collect();
+        assert_eq!(servers.len(), 2);
+    }
+
+    #[test]
+    fn empty_server_list_is_the_same_as_none() {
+        for ice_servers in [None, Some(Vec::new())] {
+            let config = RTCConfiguration {
+                ice_servers,
+                ..Default::default()
+            };
+            assert!(config.ice_servers().is_empty());
+
+            let raw: RawRTCPeerConnectionConfigure = (&config).try_into().unwrap();
+            assert!(raw.ice_servers.is_null());
+            assert_eq!(raw.ice_servers_size, 0);
+            assert_eq!(raw.ice_servers_capacity, 0);
+        }
+    }
+
+    #[test]
+    fn raw_arrays_drop_with_their_true_capacity() {
+        let leaked = outstanding(|| {
+            let mut urls = Vec::with_capacity(8);
+            urls.push(to_c_str("stun:a.example.com").unwrap());
+            urls.push(to_c_str("stun:b.example.com").unwrap());
+
+            let (urls, urls_size, urls_capacity) = urls.into_c_layout();
+            assert_eq!(urls_size, urls_capacity);
+            let server = RawRTCIceServer {
+                credential: std::ptr::null(),
+                username: std::ptr::null(),
+                urls_capacity: urls_capacity as c_int,
+                urls_size: urls_size as c_int,
+                urls,
+            };
+
+            let mut servers = Vec::with_capacity(4);
+            servers.push(server);
+
+            let (ice_servers, ice_servers_size, ice_servers_capacity) = servers.into_c_layout();
+            assert_eq!(ice_servers_size, ice_servers_capacity);
+            drop(RawRTCPeerConnectionConfigure {
+                bundle_policy: 0,
+                ice_transport_policy: 0,
+                peer_identity: std::ptr::null(),
+                rtcp_mux_policy: 0,
+                ice_servers_capacity: ice_servers_capacity as c_int,
+                ice_servers_size: ice_servers_size as c_int,
+                ice_servers,
+                ice_candidate_pool_size: 0,
+            });
+        });
+        assert_eq!(leaked, (0, 0));
+    }
+
+    #[test]
+    fn iterate_configured_servers() {
+        let stun = RTCIceServer::stun("stun:a.example.com");
+        let turn = RTCIceServer::turn("turn:b.example.com", "user", "pass");
+        let config = RTCConfiguration::builder()
+            .add_ice_server(stun.clone())
+            .add_ice_server(turn.clone())
+            .build();
+
+        let mut servers = Vec::new();
+        for server in &config {
+            servers.push(server.clone());
+        }
+
+        assert_eq!(servers, [stun, turn]);
+        assert_eq!(config.as_ref(), config.ice_servers());
+        assert_eq!((&RTCConfiguration::default()).into_iter().count(), 0);
+    }
+
+    #[test]
+    fn configuration_json_round_trip() {
+        let config = RTCConfiguration::builder()
+            .bundle_policy(BundlePolicy::MaxBundle)
+            .ice_transport_policy(IceTransportPolicy::Relay)
+            .rtcp_mux_policy(RtcpMuxPolicy::Require)
+            .peer_identity("peer")
+            .add_ice_server(RTCIceServer::stun("stun:a.example.com"))
+            .add_ice_server(RTCIceServer::turn(
+                &["turn:b.example.com", "turns:b.example.com"][..],
+                "user",
+                "pass",
+            ))
+            .ice_candidate_pool_size(2)
+            .build();
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.contains(r#""bundle_policy":"max-bundle""#));
+        assert!(json.contains(r#""ice_transport_policy":"relay""#));
+        assert!(json.contains(r#""rtcp_mux_policy":"require""#));
+        assert_eq!(
+            serde_json::from_str::<RTCConfiguration>(&json).unwrap(),
+            config
+        );
+
+        // `urls` may be a single string, as in the WebRTC API.
+        let json = r#"{
+            "bundle_policy": "max-compat",
+            "ice_transport_policy": "none",
+            "rtcp_mux_policy": "negotiate",
+            "ice_servers": [
+                { "urls": "stun:a.example.com" },
+                { "urls": ["turn:b.example.com"], "username": "user", "credential": "pass" }
+            ]
+        }"#;
+        let expected = RTCConfiguration::builder()
+            .bundle_policy(BundlePolicy::MaxCompat)
+            .ice_transport_policy(IceTransportPolicy::None)
+            .rtcp_mux_policy(RtcpMuxPolicy::Negotiate)
+            .add_ice_server(RTCIceServer::stun("stun:a.example.com"))
+            .add_ice_server(RTCIceServer::turn("turn:b.example.com", "user", "pass"))
+            .build();
+        assert_eq!(
+            serde_json::from_str::<RTCConfiguration>(json).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn policies_convert_to_their_discriminants() {
+        let raw_policies = |config: RTCConfiguration| {
+            let raw: RawRTCPeerConnectionConfigure = (&config).try_into().unwrap();
+            (
+                raw.bundle_policy,
+                raw.ice_transport_policy,
+                raw.rtcp_mux_policy,
+            )
+        };
+
+        // Unset is 0, so it never collides with IceTransportPolicy::None.
+        assert_eq!(raw_policies(RTCConfiguration::default()), (0, 0, 0));
+
+        let cases = [
+            (
+                BundlePolicy::Balanced,
+                IceTransportPolicy::None,
+                RtcpMuxPolicy::Negotiate,
+                (1, 1, 1),
+            ),
+            (
+                BundlePolicy::MaxCompat,
+                IceTransportPolicy::Relay,
+                RtcpMuxPolicy::Require,
+                (2, 2, 2),
+            ),
+            (
+                BundlePolicy::MaxBundle,
+                IceTransportPolicy::Public,
+                RtcpMuxPolicy::Require,
+                (3, 3, 2),
+            ),
+            (
+                BundlePolicy::MaxBundle,
+                IceTransportPolicy::All,
+                RtcpMuxPolicy::Require,
+                (3, 4, 2),
+            ),
+        ];
+
+        for (bundle, transport, rtcp_mux, expected) in cases {
+            let config = RTCConfiguration::builder()
+                .bundle_policy(bundle)
+                .ice_transport_policy(transport)
+                .rtcp_mux_policy(rtcp_mux)
+                .build();
+            assert_eq!(raw_policies(config), expected);
+        }
+    }
+
+    #[test]
+    fn candidate_pool_size_converts() {
+        let raw: RawRTCPeerConnectionConfigure = (&RTCConfiguration::default()).try_into().unwrap();
+        assert_eq!(raw.ice_candidate_pool_size, 0);
+
+        let config = RTCConfiguration::builder()
+            .ice_candidate_pool_size(u8::MAX)
+            .build();
+        let raw: RawRTCPeerConnectionConfigure = (&config).try_into().unwrap();
+        assert_eq!(raw.ice_candidate_pool_size, 255);
+    }
+
+    #[test]
+    fn errors_display_a_message() {
+        let err = RTCIceServer::with_urls("turn:a.example.com")
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "\"turn:a.example.com\" needs a username and a credential"
+        );
+        assert_eq!(
+            ConfigError::IceServerError(IceServerError::MissingUrls).to_string(),
+            "invalid ICE server: the ICE server has no urls"
+        );
     }
 }
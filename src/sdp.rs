@@ -0,0 +1,341 @@
+use std::{error::Error, fmt, str::FromStr};
+
+/// Reasons a string is rejected by [`Sdp::from_str`].
+#[derive(Debug)]
+pub enum SdpError {
+    /// The description does not start with a `v=` line.
+    MissingVersion,
+    /// The line at this zero-based index is not of the form `<type>=<value>`.
+    InvalidLine(usize),
+}
+
+impl Error for SdpError {}
+
+impl fmt::Display for SdpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingVersion => write!(f, "sdp does not start with a version line"),
+            Self::InvalidLine(index) => write!(f, "invalid sdp line at index {}", index),
+        }
+    }
+}
+
+/// One `m=` section of a session description, from its media line up to the
+/// next one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MediaSection {
+    lines: Vec<String>,
+}
+
+impl MediaSection {
+    /// The media type of the section, such as "audio", "video" or
+    /// "application".
+    pub fn kind(&self) -> &str {
+        self.lines[0][2..].split(' ').next().unwrap_or_default()
+    }
+
+    /// The value of every `a=` attribute with this name, `None` for flag
+    /// attributes such as `a=rtcp-mux`.
+    pub fn attributes<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Option<&'a str>> {
+        self.lines.iter().filter_map(move |line| {
+            let attr = line.strip_prefix("a=")?;
+            match attr.split_once(':') {
+                Some((key, value)) if key == name => Some(Some(value)),
+                None if attr == name => Some(None),
+                _ => None,
+            }
+        })
+    }
+
+    /// The raw lines of the section, starting with the `m=` line.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Removes a codec by its `a=rtpmap` encoding name, compared case
+    /// insensitively, along with its `a=fmtp` and `a=rtcp-fb` lines and the
+    /// retransmission payloads bound to it through `apt=`. Returns whether
+    /// anything was removed.
+    ///
+    /// A media line must keep at least one format, so nothing is removed if
+    /// the codec and its retransmission payloads are all the section has.
+    pub fn remove_codec(&mut self, name: &str) -> bool {
+        let mut payloads: Vec<String> = self
+            .attributes("rtpmap")
+            .flatten()
+            .filter_map(|value| {
+                let (pt, encoding) = value.split_once(' ')?;
+                let codec = encoding.split('/').next()?;
+                codec.eq_ignore_ascii_case(name).then(|| pt.to_string())
+            })
+            .collect();
+
+        if payloads.is_empty() {
+            return false;
+        }
+
+        let rtx: Vec<String> = self
+            .attributes("fmtp")
+            .flatten()
+            .filter_map(|value| {
+                let (pt, params) = value.split_once(' ')?;
+                params
+                    .split(';')
+                    .filter_map(|param| param.trim().strip_prefix("apt="))
+                    .any(|apt| payloads.iter().any(|p| p == apt))
+                    .then(|| pt.to_string())
+            })
+            .collect();
+        payloads.extend(rtx);
+
+        // m=<media> <port> <proto> <fmt> ...
+        let mut formats = self.lines[0].split(' ').skip(3);
+        if formats.all(|fmt| payloads.iter().any(|p| p == fmt)) {
+            return false;
+        }
+
+        self.lines.retain(|line| {
+            !["a=rtpmap:", "a=fmtp:", "a=rtcp-fb:"].iter().any(|prefix| {
+                line.strip_prefix(prefix)
+                    .and_then(|value| value.split(' ').next())
+                    .map(|pt| payloads.iter().any(|p| p == pt))
+                    .unwrap_or(false)
+            })
+        });
+
+        let media = self.lines[0]
+            .split(' ')
+            .enumerate()
+            .filter(|(i, fmt)| *i < 3 || !payloads.iter().any(|p| p == fmt))
+            .map(|(_, item)| item)
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.lines[0] = media;
+        true
+    }
+
+    /// Sets the `b=<kind>:<value>` line of the section, such as `AS` in
+    /// kilobits per second or `TIAS` in bits per second, replacing an
+    /// existing line of the same kind.
+    pub fn set_bandwidth(&mut self, kind: &str, value: u32) {
+        let line = format!("b={}:{}", kind, value);
+        let prefix = format!("b={}:", kind);
+        if let Some(existing) = self.lines.iter_mut().find(|l| l.starts_with(&prefix)) {
+            *existing = line;
+            return;
+        }
+
+        // b= lines follow the m=, i= and c= lines and any other b= lines.
+        let index = self
+            .lines
+            .iter()
+            .skip(1)
+            .take_while(|l| ["i=", "c=", "b="].iter().any(|p| l.starts_with(p)))
+            .count()
+            + 1;
+        self.lines.insert(index, line);
+    }
+}
+
+/// A minimal session description for munging SDP before it is applied.
+///
+/// The description is split into its session level lines and its media
+/// sections, every line is kept as is, so lines it does not understand
+/// survive a parse and serialize round trip untouched.
+///
+/// ```no_run
+/// # fn run(mut desc: librtc::RTCSessionDescription) -> Result<(), librtc::SdpError> {
+/// use librtc::Sdp;
+///
+/// let mut sdp: Sdp = desc.sdp.parse()?;
+/// sdp.remove_codec("red");
+/// sdp.set_bandwidth("AS", 500);
+/// desc.sdp = sdp.to_string();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sdp {
+    session: Vec<String>,
+    media: Vec<MediaSection>,
+}
+
+impl Sdp {
+    /// The session level lines, from `v=` up to the first `m=` line.
+    pub fn session(&self) -> &[String] {
+        &self.session
+    }
+
+    /// The media sections, in the order of their `m=` lines.
+    pub fn media(&self) -> &[MediaSection] {
+        &self.media
+    }
+
+    /// The media sections, for editing them one by one. Sections can be
+    /// changed but not added or removed, since that would break the
+    /// negotiated m-line order.
+    pub fn media_mut(&mut self) -> &mut [MediaSection] {
+        &mut self.media
+    }
+
+    /// Removes a codec from every media section, see
+    /// [`MediaSection::remove_codec`].
+    pub fn remove_codec(&mut self, name: &str) -> bool {
+        let mut removed = false;
+        for media in &mut self.media {
+            removed |= media.remove_codec(name);
+        }
+
+        removed
+    }
+
+    /// Sets the bandwidth of every media section, see
+    /// [`MediaSection::set_bandwidth`].
+    pub fn set_bandwidth(&mut self, kind: &str, value: u32) {
+        for media in &mut self.media {
+            media.set_bandwidth(kind, value);
+        }
+    }
+}
+
+impl FromStr for Sdp {
+    type Err = SdpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut sdp = Self {
+            session: Vec::new(),
+            media: Vec::new(),
+        };
+
+        let lines = s
+            .split('\n')
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.is_empty());
+        for (index, line) in lines.enumerate() {
+            let bytes = line.as_bytes();
+            if bytes.len() < 2 || bytes[1] != b'=' {
+                return Err(SdpError::InvalidLine(index));
+            }
+
+            if index == 0 && !line.starts_with("v=") {
+                return Err(SdpError::MissingVersion);
+            }
+
+            if line.starts_with("m=") {
+                sdp.media.push(MediaSection {
+                    lines: vec![line.to_string()],
+                });
+            } else if let Some(media) = sdp.media.last_mut() {
+                media.lines.push(line.to_string());
+            } else {
+                sdp.session.push(line.to_string());
+            }
+        }
+
+        if sdp.session.is_empty() {
+            return Err(SdpError::MissingVersion);
+        }
+
+        Ok(sdp)
+    }
+}
+
+impl fmt::Display for Sdp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let media = self.media.iter().flat_map(|m| m.lines.iter());
+        for line in self.session.iter().chain(media) {
+            write!(f, "{}\r\n", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFFER: &str = "v=0\r\n\
+        o=- 4611731400430051336 2 IN IP4 127.0.0.1\r\n\
+        s=-\r\n\
+        t=0 0\r\n\
+        a=group:BUNDLE 0 1\r\n\
+        m=audio 9 UDP/TLS/RTP/SAVPF 111 63\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        a=mid:0\r\n\
+        a=rtcp-mux\r\n\
+        a=rtpmap:111 opus/48000/2\r\n\
+        a=rtcp-fb:111 transport-cc\r\n\
+        a=fmtp:111 minptime=10;useinbandfec=1\r\n\
+        a=rtpmap:63 red/48000/2\r\n\
+        a=fmtp:63 111/111\r\n\
+        m=video 9 UDP/TLS/RTP/SAVPF 96 97 98 99\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        a=mid:1\r\n\
+        a=rtpmap:96 VP8/90000\r\n\
+        a=rtcp-fb:96 nack\r\n\
+        a=rtpmap:97 rtx/90000\r\n\
+        a=fmtp:97 apt=96\r\n\
+        a=rtpmap:98 red/90000\r\n\
+        a=rtpmap:99 rtx/90000\r\n\
+        a=fmtp:99 apt=98\r\n";
+
+    #[test]
+    fn parse_and_serialize_round_trip() {
+        let sdp: Sdp = OFFER.parse().unwrap();
+        assert_eq!(sdp.session().len(), 5);
+        assert_eq!(sdp.media().len(), 2);
+        assert_eq!(sdp.media()[0].kind(), "audio");
+        assert_eq!(sdp.media()[1].kind(), "video");
+        assert_eq!(
+            sdp.media()[0].attributes("rtcp-mux").collect::<Vec<_>>(),
+            [None]
+        );
+        assert_eq!(sdp.to_string(), OFFER);
+        assert_eq!(sdp.to_string().parse::<Sdp>().unwrap(), sdp);
+    }
+
+    #[test]
+    fn remove_red_codec() {
+        let mut sdp: Sdp = OFFER.parse().unwrap();
+        assert!(sdp.remove_codec("RED"));
+
+        let audio = &sdp.media()[0];
+        assert_eq!(audio.lines()[0], "m=audio 9 UDP/TLS/RTP/SAVPF 111");
+        assert!(!audio.lines().iter().any(|l| l.contains(":63 ")));
+        assert_eq!(audio.attributes("rtpmap").count(), 1);
+
+        // The rtx payload bound to red goes with it, VP8 and its rtx stay.
+        let video = &sdp.media()[1];
+        assert_eq!(video.lines()[0], "m=video 9 UDP/TLS/RTP/SAVPF 96 97");
+        assert_eq!(
+            video.attributes("fmtp").collect::<Vec<_>>(),
+            [Some("97 apt=96")]
+        );
+
+        assert!(!sdp.remove_codec("red"));
+    }
+
+    #[test]
+    fn last_codec_is_kept() {
+        let mut sdp: Sdp = OFFER.parse().unwrap();
+        assert!(sdp.media_mut()[1].remove_codec("red"));
+        assert!(!sdp.media_mut()[1].remove_codec("vp8"));
+        assert_eq!(
+            sdp.media()[1].lines()[0],
+            "m=video 9 UDP/TLS/RTP/SAVPF 96 97"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        assert!(matches!(
+            "o=- 0 0 IN IP4 127.0.0.1".parse::<Sdp>(),
+            Err(SdpError::MissingVersion)
+        ));
+        assert!(matches!(
+            "v=0\r\nnot a line\r\n".parse::<Sdp>(),
+            Err(SdpError::InvalidLine(1))
+        ));
+    }
+}
@@ -3,7 +3,7 @@ use std::{
     ffi::{c_char, c_void},
     fmt,
     sync::{
-        atomic::{AtomicPtr, Ordering},
+        atomic::{AtomicI32, Ordering},
         Arc,
     },
 };
@@ -12,9 +12,11 @@ use futures::task::AtomicWaker;
 
 use crate::{
     cstr::{from_c_str, StringError},
-    rtc_peerconnection::RawRTCPeerConnection,
+    promisify::CallbackSlot,
+    rtc_icecandidate::PendingCandidates,
+    rtc_peerconnection::{hold_open, Closed, RawRTCPeerConnection},
     rtc_session_description::RawRTCSessionDescription,
-    Promisify, PromisifyExt, RTCSessionDescription,
+    Promisify, PromisifyExt, RTCSessionDescription, RTCSessionDescriptionType, SignalingState,
 };
 
 extern "C" {
@@ -37,13 +39,28 @@ extern "C" {
 pub enum SetDescriptionError {
     StringError(StringError),
     SetFailed(String),
+    /// The peer connection has been closed.
+    Closed,
+    /// The description has no SDP, only a rollback may be empty.
+    EmptySdp,
+    /// The description type cannot be applied in the connection's current
+    /// signaling state, such as a remote answer without a local offer.
+    InvalidState(SignalingState),
 }
 
 impl Error for SetDescriptionError {}
 
 impl fmt::Display for SetDescriptionError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self)
+        match self {
+            Self::StringError(e) => write!(f, "invalid string: {}", e),
+            Self::SetFailed(message) => write!(f, "failed to set the description: {}", message),
+            Self::Closed => write!(f, "the peer connection is closed"),
+            Self::EmptySdp => write!(f, "the description has no sdp"),
+            Self::InvalidState(state) => {
+                write!(f, "the description cannot be set in the {:?} state", state)
+            }
+        }
     }
 }
 
@@ -53,6 +70,28 @@ pub(crate) enum SetDescriptionKind {
     Remote,
 }
 
+impl SetDescriptionKind {
+    /// Whether a description of this kind and type may be applied in the
+    /// given signaling state, following the JSEP state machine.
+    fn is_legal(&self, kind: RTCSessionDescriptionType, state: SignalingState) -> bool {
+        use RTCSessionDescriptionType::*;
+        use SignalingState::*;
+
+        match (self, kind) {
+            (Self::Local, Offer) => matches!(state, Stable | HaveLocalOffer),
+            (Self::Local, Answer | PrAnswer) => {
+                matches!(state, HaveRemoteOffer | HaveLocalPrAnswer)
+            }
+            (Self::Local, Rollback) => matches!(state, HaveLocalOffer | HaveRemoteOffer),
+            (Self::Remote, Offer) => matches!(state, Stable | HaveRemoteOffer),
+            (Self::Remote, Answer | PrAnswer) => {
+                matches!(state, HaveLocalOffer | HaveRemotePrAnswer)
+            }
+            (Self::Remote, Rollback) => matches!(state, HaveLocalOffer | HaveRemoteOffer),
+        }
+    }
+}
+
 struct SetDescriptionContext {
     callback: Box<dyn FnMut(Result<(), SetDescriptionError>)>,
 }
@@ -75,7 +114,10 @@ pub struct SetDescriptionObserver<'a> {
     kind: SetDescriptionKind,
     desc: &'a RTCSessionDescription,
     pc: *const RawRTCPeerConnection,
-    ret: Arc<AtomicPtr<Result<(), SetDescriptionError>>>,
+    closed: Closed,
+    state: Arc<AtomicI32>,
+    candidates: Arc<PendingCandidates>,
+    ret: Arc<CallbackSlot<Result<(), SetDescriptionError>>>,
 }
 
 unsafe impl Send for SetDescriptionObserver<'_> {}
@@ -86,18 +128,41 @@ impl<'a> PromisifyExt for SetDescriptionObserver<'a> {
     type Output = ();
 
     fn handle(&self, waker: Arc<AtomicWaker>) -> Result<(), Self::Err> {
+        let _open = hold_open(&self.closed).ok_or(SetDescriptionError::Closed)?;
+
+        if self.desc.sdp.is_empty()
+            && !matches!(self.desc.kind, RTCSessionDescriptionType::Rollback)
+        {
+            return Err(SetDescriptionError::EmptySdp);
+        }
+
+        let state = SignalingState::from(self.state.load(Ordering::Acquire));
+        if !self.kind.is_legal(self.desc.kind, state) {
+            return Err(SetDescriptionError::InvalidState(state));
+        }
+
+        let desc: RawRTCSessionDescription = self
+            .desc
+            .try_into()
+            .map_err(|e| SetDescriptionError::StringError(e))?;
+
         let ret = self.ret.clone();
+        let flush = (self.kind == SetDescriptionKind::Remote)
+            .then(|| (self.pc, self.closed.clone(), self.candidates.clone()));
         let ctx = Box::into_raw(Box::new(SetDescriptionContext {
             callback: Box::new(move |res| {
-                ret.store(Box::into_raw(Box::new(res)), Ordering::Relaxed);
+                // See PendingCandidates::flush.
+                if let Some((pc, closed, candidates)) = flush.as_ref().filter(|_| res.is_ok()) {
+                    if let Some(_open) = hold_open(closed) {
+                        candidates.flush(*pc);
+                    }
+                }
+
+                ret.put(res);
                 waker.wake();
             }),
         })) as *mut c_void;
 
-        let desc: RawRTCSessionDescription = self
-            .desc
-            .try_into()
-            .map_err(|e| SetDescriptionError::StringError(e))?;
         if self.kind == SetDescriptionKind::Local {
             unsafe { rtc_set_local_description(self.pc, &desc, set_description_callback, ctx) };
         } else {
@@ -108,12 +173,7 @@ impl<'a> PromisifyExt for SetDescriptionObserver<'a> {
     }
 
     fn wake(&self) -> Option<Result<Self::Output, Self::Err>> {
-        unsafe {
-            self.ret
-                .swap(std::ptr::null_mut(), Ordering::Relaxed)
-                .as_mut()
-        }
-        .map(|ptr| unsafe { *Box::from_raw(ptr) })
+        self.ret.take()
     }
 }
 
@@ -123,12 +183,66 @@ impl<'a> SetDescriptionFuture<'a> {
         pc: *const RawRTCPeerConnection,
         desc: &'a RTCSessionDescription,
         kind: SetDescriptionKind,
+        closed: Closed,
+        state: Arc<AtomicI32>,
+        candidates: Arc<PendingCandidates>,
     ) -> Self {
         Promisify::new(SetDescriptionObserver {
-            ret: Arc::new(AtomicPtr::new(std::ptr::null_mut())),
+            ret: Arc::new(CallbackSlot::new()),
             desc,
+            candidates,
+            closed,
+            state,
             kind,
             pc,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use RTCSessionDescriptionType::*;
+    use SignalingState::*;
+
+    #[test]
+    fn offer_answer_exchange_is_legal() {
+        // Offerer: set local offer, then remote answer.
+        assert!(SetDescriptionKind::Local.is_legal(Offer, Stable));
+        assert!(SetDescriptionKind::Remote.is_legal(Answer, HaveLocalOffer));
+        // Answerer: set remote offer, then local answer.
+        assert!(SetDescriptionKind::Remote.is_legal(Offer, Stable));
+        assert!(SetDescriptionKind::Local.is_legal(Answer, HaveRemoteOffer));
+
+        for kind in [SetDescriptionKind::Local, SetDescriptionKind::Remote] {
+            assert!(kind.is_legal(Rollback, HaveLocalOffer));
+            assert!(kind.is_legal(Rollback, HaveRemoteOffer));
+        }
+    }
+
+    #[test]
+    fn out_of_order_descriptions_are_illegal() {
+        assert!(!SetDescriptionKind::Remote.is_legal(Answer, Stable));
+        assert!(!SetDescriptionKind::Local.is_legal(Answer, Stable));
+        assert!(!SetDescriptionKind::Local.is_legal(Offer, HaveRemoteOffer));
+        assert!(!SetDescriptionKind::Remote.is_legal(Offer, HaveLocalOffer));
+
+        for kind in [SetDescriptionKind::Local, SetDescriptionKind::Remote] {
+            assert!(!kind.is_legal(Rollback, Stable));
+            assert!(!kind.is_legal(Offer, Closed));
+        }
+    }
+
+    #[test]
+    fn errors_display_a_message() {
+        assert_eq!(
+            SetDescriptionError::EmptySdp.to_string(),
+            "the description has no sdp"
+        );
+        assert_eq!(
+            SetDescriptionError::InvalidState(HaveLocalOffer).to_string(),
+            "the description cannot be set in the HaveLocalOffer state"
+        );
+    }
+}
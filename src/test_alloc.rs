@@ -0,0 +1,79 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+/// Wraps the system allocator and counts the allocations and bytes that are
+/// outstanding on each thread, so tests running in parallel don't see each
+/// other's allocations.
+struct CountingAllocator;
+
+thread_local! {
+    static OUTSTANDING: Cell<(isize, isize)> = const { Cell::new((0, 0)) };
+}
+
+fn record(allocations: isize, bytes: isize) {
+    // The slot is gone while the thread is being torn down.
+    let _ = OUTSTANDING.try_with(|outstanding| {
+        let (count, size) = outstanding.get();
+        outstanding.set((count + allocations, size + bytes));
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record(1, layout.size() as isize);
+        }
+
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record(1, layout.size() as isize);
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record(-1, -(layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record(0, new_size as isize - layout.size() as isize);
+        }
+
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Runs `f` and returns the number of allocations and bytes it left
+/// outstanding on the current thread, `(0, 0)` if it freed everything it
+/// allocated with the layouts it allocated them with.
+pub(crate) fn outstanding<F: FnOnce()>(f: F) -> (isize, isize) {
+    let before = OUTSTANDING.with(Cell::get);
+    f();
+    let after = OUTSTANDING.with(Cell::get);
+    (after.0 - before.0, after.1 - before.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_leaks() {
+        assert_eq!(outstanding(|| drop(vec![0u8; 16])), (0, 0));
+        assert_eq!(outstanding(|| std::mem::forget(vec![0u8; 16])), (1, 16));
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex, OnceLock, Weak},
+};
+
+use futures::channel::oneshot;
+
+use crate::{
+    CreateDescriptionError, DataChannelOptions, Observer, PeerConnectionState, RTCConfiguration,
+    RTCDataChannel, RTCError, RTCIceCandidate, RTCPeerConnection, SetDescriptionError,
+};
+
+#[derive(Debug)]
+pub enum LoopbackError {
+    RTCError(RTCError),
+    CreateDescriptionError(CreateDescriptionError),
+    SetDescriptionError(SetDescriptionError),
+    /// One of the peers reached the failed or closed state, or was dropped,
+    /// before it connected.
+    ConnectFailed(PeerConnectionState),
+}
+
+impl Error for LoopbackError {}
+
+impl fmt::Display for LoopbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RTCError(e) => e.fmt(f),
+            Self::CreateDescriptionError(e) => e.fmt(f),
+            Self::SetDescriptionError(e) => e.fmt(f),
+            Self::ConnectFailed(state) => write!(f, "the peers did not connect: {:?}", state),
+        }
+    }
+}
+
+type ConnectedReceiver = oneshot::Receiver<PeerConnectionState>;
+
+/// Two peer connections connected to each other by [`loopback`].
+pub struct Loopback {
+    pub offerer: Arc<RTCPeerConnection>,
+    pub answerer: Arc<RTCPeerConnection>,
+    /// The data channel labelled "loopback" that the offerer opened to have
+    /// something to negotiate.
+    pub channel: RTCDataChannel,
+}
+
+/// Forwards every gathered candidate to the other peer and reports the
+/// first final connection state.
+struct LoopbackObserver {
+    remote: Arc<OnceLock<Weak<RTCPeerConnection>>>,
+    state: Mutex<Option<oneshot::Sender<PeerConnectionState>>>,
+}
+
+impl Observer for LoopbackObserver {
+    fn on_ice_candidate(&self, candidate: RTCIceCandidate) {
+        if let Some(remote) = self.remote.get().and_then(Weak::upgrade) {
+            let _ = remote.add_ice_candidate(&candidate);
+        }
+    }
+
+    fn on_connection_change(&self, state: PeerConnectionState) {
+        if matches!(
+            state,
+            PeerConnectionState::Connected
+                | PeerConnectionState::Failed
+                | PeerConnectionState::Close
+        ) {
+            if let Some(tx) = self.state.lock().unwrap().take() {
+                let _ = tx.send(state);
+            }
+        }
+    }
+}
+
+fn create_peer(
+    config: &RTCConfiguration,
+    remote: Arc<OnceLock<Weak<RTCPeerConnection>>>,
+) -> Result<(Arc<RTCPeerConnection>, ConnectedReceiver), LoopbackError> {
+    let (tx, rx) = oneshot::channel();
+    let pc = RTCPeerConnection::new(
+        config,
+        LoopbackObserver {
+            state: Mutex::new(Some(tx)),
+            remote,
+        },
+    )
+    .map_err(LoopbackError::RTCError)?;
+
+    Ok((pc, rx))
+}
+
+async fn wait_connected(rx: ConnectedReceiver) -> Result<(), LoopbackError> {
+    match rx.await {
+        Ok(PeerConnectionState::Connected) => Ok(()),
+        Ok(state) => Err(LoopbackError::ConnectFailed(state)),
+        Err(_) => Err(LoopbackError::ConnectFailed(PeerConnectionState::Close)),
+    }
+}
+
+/// Creates two peer connections with the same configuration and connects
+/// them to each other in process, returning them once both report
+/// [`PeerConnectionState::Connected`].
+///
+/// The offer/answer exchange and trickled candidates are handled
+/// internally. A data channel labelled "loopback" is opened on the offerer
+/// so there is something to negotiate and returned with the peers, tracks
+/// and further channels can be added afterwards with a renegotiation.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), librtc::test_util::LoopbackError> {
+/// use librtc::{test_util::{loopback, Loopback}, RTCConfiguration};
+///
+/// let Loopback { offerer, answerer, channel } = loopback(&RTCConfiguration::default()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn loopback(config: &RTCConfiguration) -> Result<Loopback, LoopbackError> {
+    let offerer_remote = Arc::new(OnceLock::new());
+    let answerer_remote = Arc::new(OnceLock::new());
+    let (offerer, offerer_state) = create_peer(config, offerer_remote.clone())?;
+    let (answerer, answerer_state) = create_peer(config, answerer_remote.clone())?;
+    let _ = offerer_remote.set(Arc::downgrade(&answerer));
+    let _ = answerer_remote.set(Arc::downgrade(&offerer));
+
+    let channel = offerer
+        .create_data_channel("loopback", &DataChannelOptions::default())
+        .map_err(LoopbackError::RTCError)?;
+
+    let offer = offerer
+        .create_offer()
+        .await
+        .map_err(LoopbackError::CreateDescriptionError)?;
+    offerer
+        .set_local_description(&offer)
+        .await
+        .map_err(LoopbackError::SetDescriptionError)?;
+    answerer
+        .set_remote_description(&offer)
+        .await
+        .map_err(LoopbackError::SetDescriptionError)?;
+
+    let answer = answerer
+        .create_answer()
+        .await
+        .map_err(LoopbackError::CreateDescriptionError)?;
+    answerer
+        .set_local_description(&answer)
+        .await
+        .map_err(LoopbackError::SetDescriptionError)?;
+    offerer
+        .set_remote_description(&answer)
+        .await
+        .map_err(LoopbackError::SetDescriptionError)?;
+
+    wait_connected(offerer_state).await?;
+    wait_connected(answerer_state).await?;
+    Ok(Loopback {
+        offerer,
+        answerer,
+        channel,
+    })
+}
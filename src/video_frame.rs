@@ -1,4 +1,7 @@
-use std::{ffi::c_void, slice::from_raw_parts, sync::Arc};
+use std::{
+    error::Error, ffi::c_void, fmt, marker::PhantomData, ops::Deref, slice::from_raw_parts,
+    sync::Arc,
+};
 
 use crate::media_stream_track::rtc_free_frame;
 
@@ -13,6 +16,30 @@ pub(crate) struct RawVideoFrame {
     strides: [u32; 4],
 }
 
+/// Reasons a frame buffer is rejected by [`VideoFrameRef::from_i420`].
+#[derive(Debug)]
+pub enum FrameError {
+    /// The stride of the given plane is smaller than its row width.
+    InvalidStride(usize),
+    /// The given plane is shorter than its stride times its row count.
+    BufferTooSmall(usize),
+}
+
+impl Error for FrameError {}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidStride(plane) => {
+                write!(f, "the stride of plane {} is narrower than a row", plane)
+            }
+            Self::BufferTooSmall(plane) => {
+                write!(f, "plane {} is too small for its stride and rows", plane)
+            }
+        }
+    }
+}
+
 /// VideoFrame represents the frame of the video,
 /// and the format is i420 (yu12).
 ///
@@ -74,6 +101,12 @@ impl VideoFrame {
         unsafe { &*self.raw }.height
     }
 
+    /// get video frame timestamp, as passed to [`VideoFrame::new`] for
+    /// local frames or set by the native side for received frames.
+    pub fn timestamp(&self) -> usize {
+        unsafe { &*self.raw }.timestamp as usize
+    }
+
     /// get i420 frame y buffer
     pub fn data_y(&self) -> &[u8] {
         let raw = unsafe { &*self.raw };
@@ -114,6 +147,35 @@ impl VideoFrame {
     }
 }
 
+/// Checks that each i420 plane has a stride of at least its row width and
+/// holds at least stride times rows bytes.
+fn check_i420(
+    width: u32,
+    height: u32,
+    planes: [&[u8]; 3],
+    strides: [u32; 3],
+) -> Result<(), FrameError> {
+    let chroma_width = width.div_ceil(2);
+    let chroma_height = height.div_ceil(2);
+    let sizes = [
+        (width, height),
+        (chroma_width, chroma_height),
+        (chroma_width, chroma_height),
+    ];
+
+    for (i, (row_width, rows)) in sizes.into_iter().enumerate() {
+        if strides[i] < row_width {
+            return Err(FrameError::InvalidStride(i));
+        }
+
+        if planes[i].len() < strides[i] as usize * rows as usize {
+            return Err(FrameError::BufferTooSmall(i));
+        }
+    }
+
+    Ok(())
+}
+
 impl Drop for VideoFrame {
     fn drop(&mut self) {
         let raw = unsafe { &*self.raw };
@@ -128,3 +190,112 @@ impl Drop for VideoFrame {
         }
     }
 }
+
+/// A [`VideoFrame`] that borrows its planes for the lifetime `'a`.
+///
+/// `VideoFrame::new` does not copy the planes either, but nothing stops the
+/// buffers from being freed while the frame is still alive. This wrapper
+/// ties the frame to the borrow, so it can be pushed to a track without
+/// copying and without outliving the capture buffers.
+///
+/// ```no_run
+/// use librtc::{VideoFrameRef, VideoTrack};
+///
+/// fn push(track: &VideoTrack, y: &[u8], u: &[u8], v: &[u8]) {
+///     let frame = VideoFrameRef::from_i420(1920, 1080, 0, [y, u, v], [1920, 960, 960])
+///         .expect("invalid i420 frame");
+///     track.add_frame(&frame);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct VideoFrameRef<'a> {
+    frame: VideoFrame,
+    _planes: PhantomData<&'a [u8]>,
+}
+
+impl<'a> VideoFrameRef<'a> {
+    /// Borrow i420 planes as a frame, see [`VideoFrame::new`].
+    pub fn new(
+        width: u32,
+        height: u32,
+        timestamp: usize,
+        planes: [&'a [u8]; 4],
+        strides: [u32; 4],
+    ) -> Self {
+        Self {
+            frame: VideoFrame::new(width, height, timestamp, planes, strides),
+            _planes: PhantomData,
+        }
+    }
+
+    /// Borrow the y, u and v planes as a frame, checking that the strides
+    /// and buffers cover the frame size.
+    pub fn from_i420(
+        width: u32,
+        height: u32,
+        timestamp: usize,
+        planes: [&'a [u8]; 3],
+        strides: [u32; 3],
+    ) -> Result<Self, FrameError> {
+        check_i420(width, height, planes, strides)?;
+        Ok(Self::new(
+            width,
+            height,
+            timestamp,
+            [planes[0], planes[1], planes[2], &[]],
+            [strides[0], strides[1], strides[2], 0],
+        ))
+    }
+}
+
+impl Deref for VideoFrameRef<'_> {
+    type Target = VideoFrame;
+
+    fn deref(&self) -> &Self::Target {
+        &self.frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn i420_2x2_frame() {
+        let y = [0; 4];
+        let uv = [0; 1];
+        assert!(check_i420(2, 2, [&y, &uv, &uv], [2, 1, 1]).is_ok());
+        assert!(matches!(
+            check_i420(2, 2, [&y, &[], &uv], [2, 1, 1]),
+            Err(FrameError::BufferTooSmall(1))
+        ));
+    }
+
+    #[test]
+    fn i420_planes_are_checked() {
+        // 3x3 frame, the chroma planes round up to 2x2.
+        let y = [0; 9];
+        let uv = [0; 4];
+        assert!(check_i420(3, 3, [&y, &uv, &uv], [3, 2, 2]).is_ok());
+        assert!(matches!(
+            check_i420(3, 3, [&y, &uv, &uv], [3, 1, 2]),
+            Err(FrameError::InvalidStride(1))
+        ));
+        assert!(matches!(
+            check_i420(3, 3, [&y, &uv, &uv[..3]], [3, 2, 2]),
+            Err(FrameError::BufferTooSmall(2))
+        ));
+        assert!(matches!(
+            check_i420(3, 3, [&y, &uv, &uv], [4, 2, 2]),
+            Err(FrameError::BufferTooSmall(0))
+        ));
+    }
+
+    #[test]
+    fn errors_display_a_message() {
+        assert_eq!(
+            FrameError::BufferTooSmall(1).to_string(),
+            "plane 1 is too small for its stride and rows"
+        );
+    }
+}
@@ -70,7 +70,9 @@ impl VideoTrack {
     /// Push video frames to the current track, currently only
     /// supports pushing video frames in i420 format.
     ///
-    /// Only valid for local video streams.
+    /// Only valid for local video streams. The track is its own source, frames
+    /// go straight to whatever consumes it and are not queued, so frames
+    /// pushed before the track is added to a connection are dropped.
     pub fn add_frame(&self, frame: &VideoFrame) {
         unsafe {
             rtc_add_video_track_frame(self.raw, frame.get_raw());